@@ -0,0 +1,55 @@
+use crate::index::Task;
+use crate::storage::Storage;
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+
+/// The index of archived tasks, relative to the board root. Archived tasks are kept out
+/// of `index.yml` so that it stays small, and out of the default view of the board.
+pub const ARCHIVE_INDEX_FILE: &str = "archive/index.yml";
+
+/// The path of an archived task's detail file, relative to the board root
+pub fn detail_file(task_id: u64) -> String {
+    format!("archive/tasks/{:03}.md", task_id)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Archive {
+    tasks: Vec<Task>,
+}
+
+/// The archived tasks, in order of id
+pub fn load() -> Result<Vec<Task>> {
+    match Storage::current()?
+        .read_to_string(ARCHIVE_INDEX_FILE)
+        .wrap_err("reading archive index")?
+    {
+        Some(contents) => parse(&contents),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Parse the contents of an archive index
+pub fn parse(contents: &str) -> Result<Vec<Task>> {
+    let archive: Archive = serde_yaml::from_str(contents).wrap_err("parsing archive index")?;
+    Ok(archive.tasks)
+}
+
+/// Replace the archived tasks, removing the archive index if there are none left
+pub fn save(tasks: &[Task]) -> Result<()> {
+    let storage = Storage::current()?;
+    if tasks.is_empty() {
+        if storage.exists(ARCHIVE_INDEX_FILE)? {
+            storage
+                .remove(ARCHIVE_INDEX_FILE)
+                .wrap_err("deleting archive index")?;
+        }
+        return Ok(());
+    }
+    let archive = Archive {
+        tasks: tasks.to_vec(),
+    };
+    let body = serde_yaml::to_string(&archive).wrap_err("serializing archive index")?;
+    storage
+        .write(ARCHIVE_INDEX_FILE, body.as_bytes())
+        .wrap_err("writing archive index")
+}
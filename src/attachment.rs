@@ -0,0 +1,82 @@
+use crate::storage::Storage;
+use eyre::{Result, WrapErr};
+use std::path::Path;
+
+/// Attachments larger than this many bytes trigger a warning, unless the index `meta`
+/// configures a different `attachment_warn_size`
+pub const DEFAULT_WARN_SIZE: u64 = 1024 * 1024;
+
+/// The path of a task attachment, relative to the board root
+pub fn attachment_file(task_id: u64, name: &str) -> String {
+    format!("attachments/{:03}/{}", task_id, name)
+}
+
+/// Copy `source` into the attachments of a task, returning the name it was stored
+/// under and its size in bytes
+pub fn store(task_id: u64, source: &Path) -> Result<(String, u64)> {
+    let name = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| eyre::eyre!("{:?} has no usable file name", source))?
+        .to_string();
+    let storage = Storage::current()?;
+    let path = attachment_file(task_id, &name);
+    if storage.exists(&path)? {
+        return Err(eyre::eyre!(
+            "task {} already has an attachment called {}",
+            task_id,
+            name
+        ));
+    }
+
+    let contents = std::fs::read(source).wrap_err_with(|| format!("reading {:?}", source))?;
+    storage
+        .write(&path, &contents)
+        .wrap_err("writing attachment")?;
+    Ok((name, contents.len() as u64))
+}
+
+/// The size of an attachment in bytes, or `None` if it is missing
+pub fn size(task_id: u64, name: &str) -> Result<Option<u64>> {
+    let contents = Storage::current()?.read(&attachment_file(task_id, name))?;
+    Ok(contents.map(|c| c.len() as u64))
+}
+
+/// Remove every attachment of a task, whatever its detail lists
+pub fn remove_all(task_id: u64) -> Result<()> {
+    let storage = Storage::current()?;
+    for path in storage.list(&format!("attachments/{:03}", task_id))? {
+        storage
+            .remove(&path)
+            .wrap_err_with(|| format!("deleting attachment {}", path))?;
+    }
+    Ok(())
+}
+
+/// Format a size in bytes for display, e.g. `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
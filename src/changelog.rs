@@ -0,0 +1,79 @@
+use crate::index::TaskDetail;
+use std::collections::BTreeMap;
+
+/// Heading for tasks without any tags
+const UNTAGGED: &str = "Other";
+
+/// Render finished tasks as a Markdown release notes section, listing each task under
+/// every one of its tags
+pub fn render(title: &str, details: &[TaskDetail]) -> String {
+    let mut groups: BTreeMap<&str, Vec<&TaskDetail>> = BTreeMap::new();
+    let mut untagged = Vec::new();
+    for detail in details {
+        if detail.tags.is_empty() {
+            untagged.push(detail);
+        }
+        for tag in &detail.tags {
+            groups.entry(tag.as_str()).or_default().push(detail);
+        }
+    }
+
+    let mut out = format!("## {}\n", title);
+    let sections = groups
+        .into_iter()
+        .chain(std::iter::once((UNTAGGED, untagged)))
+        .filter(|(_, details)| !details.is_empty());
+    for (heading, details) in sections {
+        out.push_str(&format!("\n### {}\n\n", heading));
+        for detail in details {
+            out.push_str(&format!("- {} (#{})\n", detail.summary.trim(), detail.id));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail(id: u64, summary: &str, tags: &[&str]) -> TaskDetail {
+        TaskDetail {
+            id,
+            summary: summary.to_string(),
+            description: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            assignees: Vec::new(),
+            estimate: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn render_groups_by_every_tag() {
+        let details = vec![
+            detail(1, "Untagged task", &[]),
+            detail(2, "Fix crash", &["bug"]),
+            detail(3, "Add diff", &["feature", "bug"]),
+        ];
+
+        let rendered = render("v1.1", &details);
+        assert_eq!(
+            rendered,
+            "## v1.1
+
+### bug
+
+- Fix crash (#2)
+- Add diff (#3)
+
+### feature
+
+- Add diff (#3)
+
+### Other
+
+- Untagged task (#1)
+"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use crate::dates;
+use crate::index::{Status, Task};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::fmt;
+
+const ASCII_WIDTH: usize = 60;
+const SVG_WIDTH: f64 = 800.0;
+const SVG_HEIGHT: f64 = 400.0;
+const SVG_MARGIN: f64 = 40.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    /// Cumulative flow: tasks per status, stacked
+    Cfd,
+    /// Tasks remaining, i.e. not yet done
+    Burndown,
+}
+
+impl fmt::Display for ChartKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ChartKind::Cfd => write!(f, "cfd"),
+            ChartKind::Burndown => write!(f, "burndown"),
+        }
+    }
+}
+
+impl std::str::FromStr for ChartKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cfd" => Ok(ChartKind::Cfd),
+            "burndown" => Ok(ChartKind::Burndown),
+            other => Err(eyre::eyre!("invalid chart {}", other)),
+        }
+    }
+}
+
+/// The number of tasks in each status at the end of a day
+#[derive(Debug, PartialEq)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub todo: usize,
+    pub doing: usize,
+    pub done: usize,
+}
+
+impl Day {
+    pub fn remaining(&self) -> usize {
+        self.todo + self.doing
+    }
+
+    fn total(&self) -> usize {
+        self.todo + self.doing + self.done
+    }
+}
+
+/// The status a task had at `at`, replaying its changes
+pub fn status_at(task: &Task, at: DateTime<Utc>) -> Status {
+    task.changes
+        .iter()
+        .rev()
+        .find(|c| c.on < at)
+        .map(|c| c.to)
+        .unwrap_or(Status::None)
+}
+
+/// Reconstruct the board at the end of each day from `since` to `until` inclusive
+pub fn daily_counts(tasks: &[Task], since: NaiveDate, until: NaiveDate) -> Vec<Day> {
+    let mut days = Vec::new();
+    let mut date = since;
+    while date <= until {
+        let end_of_day = dates::midnight(date + Duration::days(1));
+        let mut day = Day {
+            date,
+            todo: 0,
+            doing: 0,
+            done: 0,
+        };
+        for task in tasks {
+            match status_at(task, end_of_day) {
+                Status::Todo => day.todo += 1,
+                Status::Doing => day.doing += 1,
+                Status::Done => day.done += 1,
+                Status::None => {}
+            }
+        }
+        days.push(day);
+        date += Duration::days(1);
+    }
+    days
+}
+
+fn scale(value: usize, max: usize, width: usize) -> usize {
+    if max == 0 {
+        return 0;
+    }
+    (value * width + max / 2) / max
+}
+
+/// Render one line per day with a horizontal bar
+pub fn render_ascii(kind: ChartKind, days: &[Day]) -> String {
+    let mut out = String::new();
+    match kind {
+        ChartKind::Cfd => {
+            let max = days.iter().map(|d| d.total()).max().unwrap_or(0);
+            out.push_str("# done  = doing  . todo\n");
+            for day in days {
+                let done = scale(day.done, max, ASCII_WIDTH);
+                let doing = scale(day.done + day.doing, max, ASCII_WIDTH) - done;
+                let todo = scale(day.total(), max, ASCII_WIDTH) - done - doing;
+                out.push_str(&format!(
+                    "{} |{}{}{}{}| {}/{}/{}\n",
+                    day.date,
+                    "#".repeat(done),
+                    "=".repeat(doing),
+                    ".".repeat(todo),
+                    " ".repeat(ASCII_WIDTH - done - doing - todo),
+                    day.done,
+                    day.doing,
+                    day.todo
+                ));
+            }
+        }
+        ChartKind::Burndown => {
+            let max = days.iter().map(|d| d.remaining()).max().unwrap_or(0);
+            out.push_str("# remaining\n");
+            for day in days {
+                let remaining = scale(day.remaining(), max, ASCII_WIDTH);
+                out.push_str(&format!(
+                    "{} |{}{}| {}\n",
+                    day.date,
+                    "#".repeat(remaining),
+                    " ".repeat(ASCII_WIDTH - remaining),
+                    day.remaining()
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Render the chart as a standalone SVG document
+pub fn render_svg(kind: ChartKind, days: &[Day]) -> String {
+    let series: Vec<(&str, &str, Vec<usize>)> = match kind {
+        ChartKind::Cfd => vec![
+            ("done", "#4caf50", days.iter().map(|d| d.done).collect()),
+            (
+                "doing",
+                "#ff9800",
+                days.iter().map(|d| d.done + d.doing).collect(),
+            ),
+            ("todo", "#90caf9", days.iter().map(|d| d.total()).collect()),
+        ],
+        ChartKind::Burndown => vec![(
+            "remaining",
+            "#f44336",
+            days.iter().map(|d| d.remaining()).collect(),
+        )],
+    };
+    let max = series
+        .iter()
+        .flat_map(|(_, _, values)| values.iter().cloned())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let plot_width = SVG_WIDTH - 2.0 * SVG_MARGIN;
+    let plot_height = SVG_HEIGHT - 2.0 * SVG_MARGIN;
+    let step = plot_width / (days.len().max(2) - 1) as f64;
+    let x = |i: usize| SVG_MARGIN + i as f64 * step;
+    let y = |v: usize| SVG_MARGIN + plot_height * (1.0 - v as f64 / max as f64);
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = SVG_WIDTH,
+        h = SVG_HEIGHT
+    );
+    out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    // draw the topmost series first so that lower series are painted over it
+    for (name, colour, values) in series.iter().rev() {
+        if values.is_empty() {
+            continue;
+        }
+        let mut points: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("{:.1},{:.1}", x(i), y(*v)))
+            .collect();
+        match kind {
+            ChartKind::Cfd => {
+                points.push(format!("{:.1},{:.1}", x(values.len() - 1), y(0)));
+                points.push(format!("{:.1},{:.1}", x(0), y(0)));
+                out.push_str(&format!(
+                    "<polygon points=\"{}\" fill=\"{}\"><title>{}</title></polygon>\n",
+                    points.join(" "),
+                    colour,
+                    name
+                ));
+            }
+            ChartKind::Burndown => {
+                out.push_str(&format!(
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"><title>{}</title></polyline>\n",
+                    points.join(" "),
+                    colour,
+                    name
+                ));
+            }
+        }
+    }
+
+    // axes and labels
+    out.push_str(&format!(
+        "<polyline points=\"{l},{t} {l},{b} {r},{b}\" fill=\"none\" stroke=\"black\"/>\n",
+        l = SVG_MARGIN,
+        t = SVG_MARGIN,
+        b = SVG_HEIGHT - SVG_MARGIN,
+        r = SVG_WIDTH - SVG_MARGIN
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"end\">{}</text>\n",
+        SVG_MARGIN - 4.0,
+        SVG_MARGIN + 4.0,
+        max
+    ));
+    if let (Some(first), Some(last)) = (days.first(), days.last()) {
+        out.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"12\">{}</text>\n",
+            SVG_MARGIN,
+            SVG_HEIGHT - SVG_MARGIN + 16.0,
+            first.date
+        ));
+        out.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"end\">{}</text>\n",
+            SVG_WIDTH - SVG_MARGIN,
+            SVG_HEIGHT - SVG_MARGIN + 16.0,
+            last.date
+        ));
+    }
+    for (i, (name, colour, _)) in series.iter().enumerate() {
+        out.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"{}\">{}</text>\n",
+            SVG_MARGIN + 80.0 * i as f64,
+            SVG_MARGIN - 12.0,
+            colour,
+            name
+        ));
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Change;
+    use chrono::TimeZone;
+
+    fn change(from: Status, to: Status, day: u32) -> Change {
+        Change {
+            from,
+            to,
+            on: Utc.with_ymd_and_hms(2021, 9, day, 12, 0, 0).unwrap(),
+            by: None,
+        }
+    }
+
+    #[test]
+    fn reconstruct_daily_counts() {
+        let tasks = vec![
+            Task {
+                id: 1,
+                status: Status::Done,
+                changes: vec![
+                    change(Status::None, Status::Todo, 1),
+                    change(Status::Todo, Status::Doing, 2),
+                    change(Status::Doing, Status::Done, 3),
+                ],
+                ..Default::default()
+            },
+            Task {
+                id: 2,
+                status: Status::Todo,
+                changes: vec![change(Status::None, Status::Todo, 2)],
+                ..Default::default()
+            },
+        ];
+
+        let days = daily_counts(
+            &tasks,
+            NaiveDate::from_ymd_opt(2021, 8, 31).unwrap(),
+            NaiveDate::from_ymd_opt(2021, 9, 3).unwrap(),
+        );
+        let counts: Vec<_> = days.iter().map(|d| (d.todo, d.doing, d.done)).collect();
+        assert_eq!(counts, vec![(0, 0, 0), (1, 0, 0), (1, 1, 0), (1, 0, 1)]);
+    }
+
+    #[test]
+    fn render_burndown() {
+        let days = vec![
+            Day {
+                date: NaiveDate::from_ymd_opt(2021, 9, 1).unwrap(),
+                todo: 2,
+                doing: 0,
+                done: 0,
+            },
+            Day {
+                date: NaiveDate::from_ymd_opt(2021, 9, 2).unwrap(),
+                todo: 0,
+                doing: 1,
+                done: 1,
+            },
+        ];
+
+        let rendered = render_ascii(ChartKind::Burndown, &days);
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines[1], format!("2021-09-01 |{}| 2", "#".repeat(60)));
+        assert_eq!(
+            lines[2],
+            format!("2021-09-02 |{}{}| 1", "#".repeat(30), " ".repeat(30))
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::git;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+
+/// The path of the file holding a task's comments, relative to the board root. Comments
+/// are kept out of the task detail file so that discussion does not conflict with
+/// edits to the description.
+pub fn comments_file(task_id: u64) -> String {
+    format!("comments/{:03}.yml", task_id)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    /// Who wrote the comment, as `Name <email>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+    pub on: DateTime<Utc>,
+    pub text: String,
+}
+
+impl Comment {
+    /// A comment by the current git user
+    pub fn new(text: impl Into<String>) -> Result<Comment> {
+        Ok(Comment {
+            by: git::user_ident()?,
+            on: Utc::now(),
+            text: text.into(),
+        })
+    }
+}
+
+/// The comments on a task, oldest first
+pub fn load(task_id: u64) -> Result<Vec<Comment>> {
+    match Storage::current()?
+        .read_to_string(&comments_file(task_id))
+        .wrap_err("reading comments")?
+    {
+        Some(contents) => parse(&contents),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn parse(contents: &str) -> Result<Vec<Comment>> {
+    serde_yaml::from_str(contents).wrap_err("parsing comments")
+}
+
+/// Append a comment to a task's thread
+pub fn add(task_id: u64, comment: Comment) -> Result<()> {
+    let mut comments = load(task_id)?;
+    comments.push(comment);
+    let body = serde_yaml::to_string(&comments).wrap_err("serializing comments")?;
+    Storage::current()?
+        .write(&comments_file(task_id), body.as_bytes())
+        .wrap_err("writing comments")
+}
+
+/// Remove a task's comments, if it has any
+pub fn remove(task_id: u64) -> Result<()> {
+    let storage = Storage::current()?;
+    let path = comments_file(task_id);
+    if storage.exists(&path)? {
+        storage.remove(&path).wrap_err("deleting comments")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comments() {
+        let text = r#"
+- by: Jane Doe <jane@example.com>
+  on: 2021-09-19T10:00:00+00:00
+  text: |
+    Should this cover the API too?
+- on: 2021-09-19T11:00:00+00:00
+  text: Yes
+"#;
+
+        let comments = parse(text).unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].text, "Should this cover the API too?\n");
+        assert_eq!(comments[1].by, None);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::git;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use eyre::{Result, WrapErr};
+
+/// The start of a day, in UTC
+pub fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    // unwrap is safe because every day has a midnight
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+}
+
+/// Parse an absolute date, either `YYYY-MM-DD` (midnight UTC) or a full RFC 3339
+/// timestamp
+pub fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(midnight(date));
+    }
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Parse a date relative to `now`: `today`, `yesterday`, or a duration ago such as `3d`
+pub fn parse_relative(s: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let midnight = midnight(now.naive_utc().date());
+    match s {
+        "today" => Some(midnight),
+        "yesterday" => Some(midnight - Duration::days(1)),
+        other => parse_duration(other).ok().map(|d| now - d),
+    }
+}
+
+/// Parse a calendar day relative to `today`: a `YYYY-MM-DD` date, `today`, `tomorrow`,
+/// a weekday such as `friday` meaning the next one after today, or an offset such as
+/// `+3d`
+pub fn parse_day(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let s = s.to_lowercase();
+    match s.as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return Some(today + Duration::days(1)),
+        _ => {}
+    }
+    if let Some(offset) = s.strip_prefix('+') {
+        return parse_duration(offset)
+            .ok()
+            .map(|d| today + Duration::days(d.num_days()));
+    }
+    let weekday: Weekday = s.parse().ok()?;
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    Some(today + Duration::days(ahead as i64))
+}
+
+/// Parse the start of a reporting window: a date, a relative date, or a git revision
+/// such as a tag in which case the commit date is used
+pub fn parse_since(s: &str) -> Result<DateTime<Utc>> {
+    if let Some(date) = parse_date(s) {
+        return Ok(date);
+    }
+    if let Some(date) = parse_relative(s, Utc::now()) {
+        return Ok(date);
+    }
+    let commit_date = git::run(&["log", "-1", "--format=%cI", s])
+        .wrap_err_with(|| format!("{} is neither a date nor a git revision", s))?;
+    let commit_date = DateTime::parse_from_rfc3339(commit_date.trim())
+        .wrap_err_with(|| format!("parsing commit date of {}", s))?;
+    Ok(commit_date.with_timezone(&Utc))
+}
+
+/// Parse a duration such as `30m`, `12h`, `7d` or `2w`
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| eyre::eyre!("duration {} is missing a unit", s))?;
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .wrap_err_with(|| format!("invalid duration {}", s))?;
+    match unit {
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        other => Err(eyre::eyre!("invalid duration unit {}", other)),
+    }
+}
+
+/// A reporting window, open ended on either side when a bound is missing
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Window {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Window {
+    pub fn contains(&self, on: DateTime<Utc>) -> bool {
+        self.since.map(|s| on >= s).unwrap_or(true) && self.until.map(|u| on < u).unwrap_or(true)
+    }
+}
+
+/// Format a duration compactly using its two most significant units, e.g. `2d 3h`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    if minutes < 1 {
+        return "<1m".to_string();
+    }
+    let (days, hours, minutes) = (minutes / (24 * 60), (minutes / 60) % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Format how long ago `on` was, e.g. `3d 2h ago`
+pub fn format_ago(on: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    format!("{} ago", format_duration(now - on))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(
+            parse_date("2021-09-19"),
+            Some(Utc.with_ymd_and_hms(2021, 9, 19, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_date("2021-09-19T10:30:00+01:00"),
+            Some(Utc.with_ymd_and_hms(2021, 9, 19, 9, 30, 0).unwrap())
+        );
+        assert_eq!(parse_date("v1.0"), None);
+    }
+
+    #[test]
+    fn parse_relative_dates() {
+        let now = Utc.with_ymd_and_hms(2021, 9, 19, 15, 0, 0).unwrap();
+        assert_eq!(
+            parse_relative("today", now),
+            Some(Utc.with_ymd_and_hms(2021, 9, 19, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_relative("yesterday", now),
+            Some(Utc.with_ymd_and_hms(2021, 9, 18, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_relative("2h", now),
+            Some(Utc.with_ymd_and_hms(2021, 9, 19, 13, 0, 0).unwrap())
+        );
+        assert_eq!(parse_relative("v1.0", now), None);
+    }
+
+    #[test]
+    fn parse_days() {
+        // 2021-09-19 was a Sunday
+        let today = NaiveDate::from_ymd_opt(2021, 9, 19).unwrap();
+        assert_eq!(
+            parse_day("2021-11-01", today),
+            Some(NaiveDate::from_ymd_opt(2021, 11, 1).unwrap())
+        );
+        assert_eq!(
+            parse_day("tomorrow", today),
+            Some(NaiveDate::from_ymd_opt(2021, 9, 20).unwrap())
+        );
+        assert_eq!(
+            parse_day("Friday", today),
+            Some(NaiveDate::from_ymd_opt(2021, 9, 24).unwrap())
+        );
+        assert_eq!(
+            parse_day("sun", today),
+            Some(NaiveDate::from_ymd_opt(2021, 9, 26).unwrap())
+        );
+        assert_eq!(
+            parse_day("+3d", today),
+            Some(NaiveDate::from_ymd_opt(2021, 9, 22).unwrap())
+        );
+        assert_eq!(parse_day("someday", today), None);
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("7d").unwrap(), Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::days(14));
+        assert_eq!(parse_duration("90m").unwrap(), Duration::minutes(90));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("7y").is_err());
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(Duration::seconds(30)), "<1m");
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(3 * 60 + 12)), "3h 12m");
+        assert_eq!(format_duration(Duration::hours(51)), "2d 3h");
+    }
+}
@@ -0,0 +1,463 @@
+use crate::archive;
+use crate::index::{self, DateKind, Index, Status, TaskDetail};
+use crate::storage::Storage;
+use chrono::NaiveDate;
+use eyre::{Result, WrapErr};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+pub struct Snapshot {
+    pub index: Index,
+    pub details: HashMap<u64, TaskDetail>,
+}
+
+impl Snapshot {
+    pub fn at(rev: &str) -> Result<Snapshot> {
+        let storage = Storage::current()?;
+        let contents = storage
+            .read_at(rev, index::INDEX_FILE)
+            .wrap_err_with(|| format!("reading index at {}", rev))?
+            .ok_or_else(|| eyre::eyre!("no index found at revision {}", rev))?;
+        let mut index =
+            Index::parse(&contents).wrap_err_with(|| format!("parsing index at {}", rev))?;
+        if let Some(contents) = storage
+            .read_at(rev, archive::ARCHIVE_INDEX_FILE)
+            .wrap_err_with(|| format!("reading archive index at {}", rev))?
+        {
+            index.archived = archive::parse(&contents)
+                .wrap_err_with(|| format!("parsing archive index at {}", rev))?;
+        }
+
+        let mut details = HashMap::new();
+        let paths = index
+            .tasks
+            .iter()
+            .map(|t| (t, index::detail_file(t.id)))
+            .chain(
+                index
+                    .archived
+                    .iter()
+                    .map(|t| (t, archive::detail_file(t.id))),
+            );
+        for (task, path) in paths {
+            if let Some(contents) = storage.read_at(rev, &path)? {
+                let detail = TaskDetail::parse(&contents)
+                    .wrap_err_with(|| format!("parsing task {} at {}", task.id, rev))?;
+                details.insert(task.id, detail);
+            }
+        }
+
+        Ok(Snapshot { index, details })
+    }
+
+    pub fn current() -> Result<Snapshot> {
+        let index = Index::load().wrap_err("loading index")?;
+        let details = current_details(&index);
+        Ok(Snapshot { index, details })
+    }
+
+    fn summary(&self, task_id: u64) -> &str {
+        self.details
+            .get(&task_id)
+            .map(|d| d.summary.as_str())
+            .unwrap_or("<missing task detail>")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Difference {
+    Added {
+        id: u64,
+        summary: String,
+        status: Status,
+    },
+    Deleted {
+        id: u64,
+        summary: String,
+    },
+    Moved {
+        id: u64,
+        summary: String,
+        from: Status,
+        to: Status,
+    },
+    Reprioritised {
+        id: u64,
+        summary: String,
+        from: Option<i64>,
+        to: Option<i64>,
+    },
+    Retitled {
+        id: u64,
+        from: String,
+        to: String,
+    },
+    Retagged {
+        id: u64,
+        summary: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    Rescheduled {
+        id: u64,
+        summary: String,
+        kind: DateKind,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+}
+
+fn format_priority(priority: Option<i64>) -> String {
+    match priority {
+        Some(p) => p.to_string(),
+        None => "none".to_string(),
+    }
+}
+
+fn format_date(date: Option<NaiveDate>) -> String {
+    match date {
+        Some(d) => d.to_string(),
+        None => "none".to_string(),
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Difference::Added {
+                id,
+                summary,
+                status,
+            } => write!(f, "+ {:03} {} [{}]", id, summary, status),
+            Difference::Deleted { id, summary } => write!(f, "- {:03} {}", id, summary),
+            Difference::Moved {
+                id,
+                summary,
+                from,
+                to,
+            } => write!(f, "~ {:03} {}: {} -> {}", id, summary, from, to),
+            Difference::Reprioritised {
+                id,
+                summary,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ {:03} {}: priority {} -> {}",
+                id,
+                summary,
+                format_priority(*from),
+                format_priority(*to)
+            ),
+            Difference::Retitled { id, from, to } => {
+                write!(f, "~ {:03} retitled: {:?} -> {:?}", id, from, to)
+            }
+            Difference::Retagged {
+                id,
+                summary,
+                added,
+                removed,
+            } => {
+                let mut tags = Vec::new();
+                tags.extend(added.iter().map(|t| format!("+{}", t)));
+                tags.extend(removed.iter().map(|t| format!("-{}", t)));
+                write!(f, "~ {:03} {}: tags {}", id, summary, tags.join(" "))
+            }
+            Difference::Rescheduled {
+                id,
+                summary,
+                kind,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ {:03} {}: {} {} -> {}",
+                id,
+                summary,
+                kind,
+                format_date(*from),
+                format_date(*to)
+            ),
+        }
+    }
+}
+
+pub fn diff(old: &Snapshot, new: &Snapshot) -> Vec<Difference> {
+    let ids: BTreeSet<u64> = [&old.index, &new.index]
+        .iter()
+        .flat_map(|index| index.tasks.iter().chain(&index.archived))
+        .map(|t| t.id)
+        .collect();
+
+    let mut differences = Vec::new();
+    for id in ids {
+        match (old.index.find_task(id), new.index.find_task(id)) {
+            (None, Some(task)) => differences.push(Difference::Added {
+                id,
+                summary: new.summary(id).to_string(),
+                status: task.status,
+            }),
+            (Some(_), None) => differences.push(Difference::Deleted {
+                id,
+                summary: old.summary(id).to_string(),
+            }),
+            (Some(before), Some(after)) => {
+                let summary = new.summary(id).to_string();
+                if before.status != after.status {
+                    differences.push(Difference::Moved {
+                        id,
+                        summary: summary.clone(),
+                        from: before.status,
+                        to: after.status,
+                    });
+                }
+                if before.priority != after.priority {
+                    differences.push(Difference::Reprioritised {
+                        id,
+                        summary: summary.clone(),
+                        from: before.priority,
+                        to: after.priority,
+                    });
+                }
+                for (kind, from, to) in [
+                    (DateKind::Due, before.due, after.due),
+                    (DateKind::Scheduled, before.scheduled, after.scheduled),
+                ] {
+                    if from != to {
+                        differences.push(Difference::Rescheduled {
+                            id,
+                            summary: summary.clone(),
+                            kind,
+                            from,
+                            to,
+                        });
+                    }
+                }
+                if let (Some(a), Some(b)) = (old.details.get(&id), new.details.get(&id)) {
+                    if a.summary != b.summary {
+                        differences.push(Difference::Retitled {
+                            id,
+                            from: a.summary.clone(),
+                            to: b.summary.clone(),
+                        });
+                    }
+                    let added: Vec<String> = b
+                        .tags
+                        .iter()
+                        .filter(|t| !a.tags.contains(t))
+                        .cloned()
+                        .collect();
+                    let removed: Vec<String> = a
+                        .tags
+                        .iter()
+                        .filter(|t| !b.tags.contains(t))
+                        .cloned()
+                        .collect();
+                    if !added.is_empty() || !removed.is_empty() {
+                        differences.push(Difference::Retagged {
+                            id,
+                            summary,
+                            added,
+                            removed,
+                        });
+                    }
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    differences
+}
+
+pub fn parse_range(range: Option<&str>, default: &str) -> (String, Option<String>) {
+    let or_default = |s: &str| {
+        if s.is_empty() {
+            default.to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    match range {
+        None => (default.to_string(), None),
+        Some(range) => match range.split_once("..") {
+            Some((from, to)) => (or_default(from), Some(or_default(to))),
+            None => (range.to_string(), None),
+        },
+    }
+}
+
+pub fn render(index: &Index) -> String {
+    // textconv is not told which revision the file came from, so only the index fields
+    // can be shown
+    let mut tasks: Vec<_> = index.tasks.iter().collect();
+    tasks.sort_by_key(|t| t.id);
+
+    let mut out = format!("Project: {}\n", index.meta.name);
+    for task in tasks {
+        let mut line = format!(
+            "{:03} {} priority {}",
+            task.id,
+            task.status,
+            format_priority(task.priority)
+        );
+        if let Some(scheduled) = task.scheduled {
+            line.push_str(&format!(" scheduled {}", scheduled));
+        }
+        if let Some(due) = task.due {
+            line.push_str(&format!(" due {}", due));
+        }
+        if let Some(milestone) = &task.milestone {
+            line.push_str(&format!(" milestone {}", milestone));
+        }
+        if let Some(parent) = task.parent {
+            line.push_str(&format!(" part of {}", parent));
+        }
+        if !task.blocked_by.is_empty() {
+            let blockers: Vec<_> = task.blocked_by.iter().map(|b| b.to_string()).collect();
+            line.push_str(&format!(" blocked by {}", blockers.join(",")));
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn current_details(index: &Index) -> HashMap<u64, TaskDetail> {
+    index
+        .tasks
+        .iter()
+        .chain(&index.archived)
+        .filter_map(|t| t.detail().ok().map(|d| (t.id, d)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{Meta, Task};
+
+    type TaskSpec<'a> = (u64, Status, Option<i64>, &'a str, &'a [&'a str]);
+
+    fn snapshot(tasks: &[TaskSpec]) -> Snapshot {
+        let mut details = HashMap::new();
+        let tasks = tasks
+            .iter()
+            .map(|(id, status, priority, summary, tags)| {
+                details.insert(
+                    *id,
+                    TaskDetail {
+                        id: *id,
+                        summary: summary.to_string(),
+                        description: String::new(),
+                        tags: tags.iter().map(|t| t.to_string()).collect(),
+                        assignees: Vec::new(),
+                        estimate: None,
+                        attachments: Vec::new(),
+                    },
+                );
+                Task {
+                    id: *id,
+                    status: *status,
+                    priority: *priority,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        Snapshot {
+            index: Index {
+                meta: Meta {
+                    name: "Foo".to_string(),
+                    ..Default::default()
+                },
+                tasks,
+                archived: vec![],
+                trashed: vec![],
+            },
+            details,
+        }
+    }
+
+    #[test]
+    fn diff_added_and_deleted() {
+        let old = snapshot(&[(1, Status::Todo, None, "First", &[])]);
+        let new = snapshot(&[(2, Status::Todo, None, "Second", &[])]);
+
+        let differences = diff(&old, &new);
+        assert_eq!(
+            differences,
+            vec![
+                Difference::Deleted {
+                    id: 1,
+                    summary: "First".to_string()
+                },
+                Difference::Added {
+                    id: 2,
+                    summary: "Second".to_string(),
+                    status: Status::Todo
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_changed_task() {
+        let old = snapshot(&[(1, Status::Todo, None, "First", &["a", "b"])]);
+        let new = snapshot(&[(1, Status::Doing, Some(1), "Renamed", &["b", "c"])]);
+
+        let rendered: Vec<String> = diff(&old, &new).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "~ 001 Renamed: Todo -> Doing",
+                "~ 001 Renamed: priority none -> 1",
+                "~ 001 retitled: \"First\" -> \"Renamed\"",
+                "~ 001 Renamed: tags +c -a",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_ignores_archiving() {
+        let old = snapshot(&[
+            (1, Status::Done, None, "First", &[]),
+            (2, Status::Todo, None, "Second", &[]),
+        ]);
+        let mut new = snapshot(&[
+            (1, Status::Done, None, "First", &[]),
+            (2, Status::Doing, None, "Second", &[]),
+        ]);
+        let archived = new.index.tasks.remove(0);
+        new.index.archived.push(archived);
+
+        let rendered: Vec<String> = diff(&old, &new).iter().map(|d| d.to_string()).collect();
+        assert_eq!(rendered, vec!["~ 002 Second: Todo -> Doing"]);
+    }
+
+    #[test]
+    fn render_index_fields_only() {
+        let mut new = snapshot(&[
+            (1, Status::Doing, Some(2), "First", &["a"]),
+            (2, Status::Todo, None, "Second", &[]),
+        ]);
+        new.index.tasks[1].blocked_by = vec![1];
+
+        assert_eq!(
+            render(&new.index),
+            "Project: Foo\n001 Doing priority 2\n002 Todo priority none blocked by 1\n"
+        );
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(parse_range(None, "HEAD"), ("HEAD".to_string(), None));
+        assert_eq!(parse_range(Some("v1"), "HEAD"), ("v1".to_string(), None));
+        assert_eq!(
+            parse_range(Some("v1..v2"), "HEAD"),
+            ("v1".to_string(), Some("v2".to_string()))
+        );
+        assert_eq!(
+            parse_range(Some("v1.."), "HEAD"),
+            ("v1".to_string(), Some("HEAD".to_string()))
+        );
+    }
+}
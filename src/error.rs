@@ -1,7 +1,15 @@
+use crate::index::Status;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum PmError {
     #[error("index already exists")]
     IndexExists,
+    #[error("{status} is at its limit of {limit} tasks")]
+    WipLimitExceeded { status: Status, limit: usize },
+    #[error(
+        "task {task_id} is blocked by unfinished tasks {}",
+        .blockers.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    Blocked { task_id: u64, blockers: Vec<u64> },
 }
@@ -0,0 +1,106 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// How much effort a task is expected to take, either in story points or hours
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawEstimate", into = "String")]
+pub enum Estimate {
+    Points(f64),
+    Hours(f64),
+}
+
+impl Estimate {
+    /// The estimate as a duration, if it was given in hours
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            Estimate::Hours(hours) => Some(Duration::seconds((hours * 3600.0) as i64)),
+            Estimate::Points(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Estimate::Points(points) => write!(f, "{}pt", points),
+            Estimate::Hours(hours) => write!(f, "{}h", hours),
+        }
+    }
+}
+
+impl std::str::FromStr for Estimate {
+    type Err = eyre::Report;
+
+    /// Parse an estimate such as `3`, `3pt` or `5pts` in points, or `4h` or `30m` in
+    /// hours
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(s.len());
+        let (amount, unit) = s.split_at(split);
+        let amount: f64 = amount
+            .parse()
+            .map_err(|_| eyre::eyre!("invalid estimate {}", s))?;
+        if amount < 0.0 {
+            return Err(eyre::eyre!("estimate {} is negative", s));
+        }
+        match unit {
+            "" | "pt" | "pts" | "p" => Ok(Estimate::Points(amount)),
+            "h" => Ok(Estimate::Hours(amount)),
+            "m" => Ok(Estimate::Hours(amount / 60.0)),
+            other => Err(eyre::eyre!("invalid estimate unit {}", other)),
+        }
+    }
+}
+
+/// An estimate as written in a task detail header, where a bare number means points
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawEstimate {
+    Number(f64),
+    Text(String),
+}
+
+impl TryFrom<RawEstimate> for Estimate {
+    type Error = eyre::Report;
+
+    fn try_from(raw: RawEstimate) -> Result<Self, Self::Error> {
+        match raw {
+            RawEstimate::Number(points) => Ok(Estimate::Points(points)),
+            RawEstimate::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Estimate> for String {
+    fn from(estimate: Estimate) -> String {
+        estimate.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_estimates() {
+        assert_eq!("3".parse::<Estimate>().unwrap(), Estimate::Points(3.0));
+        assert_eq!("5pts".parse::<Estimate>().unwrap(), Estimate::Points(5.0));
+        assert_eq!("1.5h".parse::<Estimate>().unwrap(), Estimate::Hours(1.5));
+        assert_eq!("30m".parse::<Estimate>().unwrap(), Estimate::Hours(0.5));
+        assert!("h".parse::<Estimate>().is_err());
+        assert!("3d".parse::<Estimate>().is_err());
+    }
+
+    #[test]
+    fn deserialize_estimates() {
+        let points: Estimate = serde_yaml::from_str("3").unwrap();
+        assert_eq!(points, Estimate::Points(3.0));
+        let hours: Estimate = serde_yaml::from_str("4h").unwrap();
+        assert_eq!(hours, Estimate::Hours(4.0));
+        assert_eq!(serde_yaml::to_string(&hours).unwrap().trim(), "---\n4h");
+    }
+}
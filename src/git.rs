@@ -0,0 +1,266 @@
+use chrono::{DateTime, Utc};
+use eyre::{Result, WrapErr};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Run a git command, returning its standard output
+pub fn run(args: &[&str]) -> Result<String> {
+    let stdout = run_with(args, &[], None)?;
+    String::from_utf8(stdout).wrap_err("decoding git output")
+}
+
+/// Run a git command with extra environment variables and optional standard input,
+/// returning its raw standard output
+pub fn run_with(args: &[&str], env: &[(&str, &str)], input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut command = Command::new("git");
+    command.args(args).envs(env.iter().cloned());
+    run_command(command, input)
+}
+
+/// Run a prepared git command with optional standard input, returning its raw standard
+/// output
+pub fn run_command(mut command: Command, input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let args: Vec<String> = command
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .wrap_err_with(|| format!("running git {}", args.join(" ")))?;
+
+    {
+        // unwrap is safe because stdin is piped above
+        let mut stdin = child.stdin.take().unwrap();
+        if let Some(input) = input {
+            stdin.write_all(input).wrap_err("writing to git stdin")?;
+        }
+    }
+
+    let output = child.wait_with_output().wrap_err("waiting for git")?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Fetch the contents of `path` at revision `rev`, or `None` if the file does not exist
+/// at that revision
+pub fn show(rev: &str, path: &str) -> Result<Option<String>> {
+    match show_bytes(rev, path)? {
+        Some(contents) => {
+            let contents = String::from_utf8(contents).wrap_err("decoding git output")?;
+            Ok(Some(contents))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Fetch the raw contents of `path` at revision `rev`
+pub fn show_bytes(rev: &str, path: &str) -> Result<Option<Vec<u8>>> {
+    let spec = format!("{}:{}", rev, path);
+    let output = Command::new("git")
+        .args(["cat-file", "blob", &spec])
+        .output()
+        .wrap_err_with(|| format!("running git cat-file {}", spec))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(output.stdout))
+}
+
+/// Store `contents` as a blob in the object database, returning its id
+pub fn write_blob(contents: &[u8]) -> Result<String> {
+    write_blob_with(Command::new("git"), contents)
+}
+
+/// Store `contents` as a blob using a prepared git command, e.g. one pointing at
+/// another repository
+pub fn write_blob_with(mut command: Command, contents: &[u8]) -> Result<String> {
+    command.args(["hash-object", "-w", "--stdin"]);
+    let blob = run_command(command, Some(contents)).wrap_err("writing blob")?;
+    Ok(String::from_utf8_lossy(&blob).trim().to_string())
+}
+
+/// Read a blob from the object database, or `None` if it no longer exists
+pub fn read_blob(id: &str) -> Result<Option<Vec<u8>>> {
+    let output = Command::new("git")
+        .args(["cat-file", "blob", id])
+        .output()
+        .wrap_err_with(|| format!("running git cat-file {}", id))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(output.stdout))
+}
+
+/// Read a git configuration value, or `None` if it is not set
+pub fn config(key: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .output()
+        .wrap_err_with(|| format!("reading git config {}", key))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let value = String::from_utf8(output.stdout).wrap_err("decoding git output")?;
+    Ok(Some(value.trim().to_string()))
+}
+
+/// Resolve `rev` to an object id, or `None` if it does not exist
+pub fn resolve(rev: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", rev])
+        .output()
+        .wrap_err_with(|| format!("resolving {}", rev))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let value = String::from_utf8(output.stdout).wrap_err("decoding git output")?;
+    Ok(Some(value.trim().to_string()))
+}
+
+/// A commit and who authored it
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub id: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub date: DateTime<Utc>,
+    pub subject: String,
+}
+
+/// The commits reachable from `rev` which touched any of `paths`, newest first
+pub fn log(rev: &str, paths: &[String], since: Option<DateTime<Utc>>) -> Result<Vec<Commit>> {
+    let mut args = vec![
+        "log".to_string(),
+        "--format=%H%x09%an%x09%ae%x09%aI%x09%s".to_string(),
+    ];
+    if let Some(since) = since {
+        args.push(format!("--since={}", since.to_rfc3339()));
+    }
+    args.push(rev.to_string());
+    args.push("--".to_string());
+    args.extend(paths.iter().cloned());
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+
+    let output = run(&args)?;
+    let commits = output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(5, '\t');
+            Some(Commit {
+                id: parts.next()?.to_string(),
+                author_name: parts.next()?.to_string(),
+                author_email: parts.next()?.to_string(),
+                date: DateTime::parse_from_rfc3339(parts.next()?)
+                    .ok()?
+                    .with_timezone(&Utc),
+                subject: parts.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect();
+    Ok(commits)
+}
+
+/// The user name and email configured in git
+pub fn user() -> Result<(Option<String>, Option<String>)> {
+    Ok((config("user.name")?, config("user.email")?))
+}
+
+/// The current user formatted as `Name <email>`, or `None` if git does not know who
+/// they are
+pub fn user_ident() -> Result<Option<String>> {
+    let ident = match user()? {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        (Some(name), None) => Some(name),
+        (None, Some(email)) => Some(format!("<{}>", email)),
+        (None, None) => None,
+    };
+    Ok(ident)
+}
+
+/// The names the current user may be assigned by: the local part of their git email,
+/// which is preferred as an `@name` handle, their email and their user name
+pub fn user_aliases() -> Result<Vec<String>> {
+    let (name, email) = user()?;
+    let mut aliases = Vec::new();
+    if let Some(email) = email {
+        if let Some((local, _)) = email.split_once('@') {
+            aliases.push(local.to_string());
+        }
+        aliases.push(email);
+    }
+    aliases.extend(name);
+    Ok(aliases)
+}
+
+/// Resolve a user filter given on the command line, where `me` means the current user
+pub fn resolve_who(who: &str) -> Result<String> {
+    if who != "me" {
+        return Ok(who.to_string());
+    }
+    match user()? {
+        (_, Some(email)) => Ok(email),
+        (Some(name), None) => Ok(name),
+        (None, None) => Err(eyre::eyre!("git user.name and user.email are not set")),
+    }
+}
+
+pub fn git_dir() -> Result<PathBuf> {
+    let dir = run(&["rev-parse", "--absolute-git-dir"]).wrap_err("finding git dir")?;
+    Ok(PathBuf::from(dir.trim()))
+}
+
+/// A line of a tracked file matched by `grep`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    /// The path of the file, relative to the repository root
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+}
+
+/// Search the tracked files of the whole working tree for `pattern`, skipping binary
+/// files and the `pm` directory
+pub fn grep(pattern: &str, fixed: bool) -> Result<Vec<GrepMatch>> {
+    let mut args = vec!["grep", "-z", "-n", "-I", "--full-name"];
+    args.push(if fixed { "-F" } else { "-E" });
+    args.extend(["-e", pattern, "--", ":(top)", ":(top,exclude)pm"]);
+    let output = Command::new("git")
+        .args(&args)
+        .output()
+        .wrap_err("running git grep")?;
+    // git grep exits with 1 when nothing matches
+    match output.status.code() {
+        Some(0) => {}
+        Some(1) => return Ok(Vec::new()),
+        _ => {
+            return Err(eyre::eyre!(
+                "git grep failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut matches = Vec::new();
+    for line in stdout.lines() {
+        let mut parts = line.splitn(3, '\0');
+        if let (Some(path), Some(number), Some(text)) = (parts.next(), parts.next(), parts.next()) {
+            matches.push(GrepMatch {
+                path: path.to_string(),
+                line: number.parse().wrap_err("parsing git grep line number")?,
+                text: text.to_string(),
+            });
+        }
+    }
+    Ok(matches)
+}
@@ -0,0 +1,167 @@
+use crate::index::{Status, Task};
+use std::collections::HashMap;
+
+/// A chain of dependencies leading from task `from` to task `to` by following
+/// `blocked_by`, including both ends
+pub fn dependency_path(tasks: &[Task], from: u64, to: u64) -> Option<Vec<u64>> {
+    let mut path = vec![from];
+    if search(tasks, to, &mut path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn search(tasks: &[Task], to: u64, path: &mut Vec<u64>) -> bool {
+    // unwrap is safe because the path always starts with a task
+    let current = *path.last().unwrap();
+    if current == to {
+        return true;
+    }
+    let blockers = match tasks.iter().find(|t| t.id == current) {
+        Some(task) => &task.blocked_by,
+        None => return false,
+    };
+    for blocker in blockers {
+        if path.contains(blocker) {
+            continue;
+        }
+        path.push(*blocker);
+        if search(tasks, to, path) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+/// A dependency cycle, starting and ending with the same task, if there is one
+pub fn find_cycle(tasks: &[Task]) -> Option<Vec<u64>> {
+    tasks.iter().find_map(|task| {
+        task.blocked_by.iter().find_map(|blocker| {
+            let mut path = dependency_path(tasks, *blocker, task.id)?;
+            path.insert(0, task.id);
+            Some(path)
+        })
+    })
+}
+
+/// The tasks blocked by `task_id`
+pub fn blocks(tasks: &[Task], task_id: u64) -> Vec<u64> {
+    tasks
+        .iter()
+        .filter(|t| t.blocked_by.contains(&task_id))
+        .map(|t| t.id)
+        .collect()
+}
+
+fn label(task: &Task, summaries: &HashMap<u64, String>) -> String {
+    let summary = summaries
+        .get(&task.id)
+        .map(|s| s.as_str())
+        .unwrap_or("<missing task detail>");
+    format!("{:03} {} [{}]", task.id, summary, task.status)
+}
+
+/// Render the dependency graph as an indented tree, with each task followed by the
+/// tasks it blocks. Tasks without dependencies are left out.
+pub fn render_text(tasks: &[Task], summaries: &HashMap<u64, String>) -> String {
+    let mut out = String::new();
+    let roots = tasks
+        .iter()
+        .filter(|t| t.blocked_by.is_empty() && !blocks(tasks, t.id).is_empty());
+    for root in roots {
+        render_tree(tasks, summaries, root, &mut vec![root.id], &mut out);
+    }
+    out
+}
+
+fn render_tree(
+    tasks: &[Task],
+    summaries: &HashMap<u64, String>,
+    task: &Task,
+    path: &mut Vec<u64>,
+    out: &mut String,
+) {
+    out.push_str(&"    ".repeat(path.len() - 1));
+    out.push_str(&label(task, summaries));
+    out.push('\n');
+    for blocked in tasks.iter().filter(|t| t.blocked_by.contains(&task.id)) {
+        if path.contains(&blocked.id) {
+            continue;
+        }
+        path.push(blocked.id);
+        render_tree(tasks, summaries, blocked, path, out);
+        path.pop();
+    }
+}
+
+/// Render the dependency graph in Graphviz DOT format, with edges pointing from each
+/// task to the tasks it blocks
+pub fn render_dot(tasks: &[Task], summaries: &HashMap<u64, String>) -> String {
+    let mut out = String::from("digraph pm {\n    node [shape=box];\n");
+    let related = tasks
+        .iter()
+        .filter(|t| !t.blocked_by.is_empty() || !blocks(tasks, t.id).is_empty());
+    for task in related {
+        let style = match task.status {
+            Status::Done => ", style=dashed",
+            _ => "",
+        };
+        out.push_str(&format!(
+            "    {} [label=\"{}\"{}];\n",
+            task.id,
+            label(task, summaries)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\""),
+            style
+        ));
+    }
+    for task in tasks {
+        for blocker in &task.blocked_by {
+            out.push_str(&format!("    {} -> {};\n", blocker, task.id));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u64, blocked_by: &[u64]) -> Task {
+        Task {
+            id,
+            status: Status::Todo,
+            blocked_by: blocked_by.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_dependency_paths_and_cycles() {
+        let tasks = vec![task(1, &[]), task(2, &[1]), task(3, &[2]), task(4, &[])];
+
+        assert_eq!(dependency_path(&tasks, 3, 1), Some(vec![3, 2, 1]));
+        assert_eq!(dependency_path(&tasks, 1, 3), None);
+        assert_eq!(blocks(&tasks, 1), vec![2]);
+        assert_eq!(find_cycle(&tasks), None);
+
+        let cyclic = vec![task(1, &[3]), task(2, &[1]), task(3, &[2])];
+        assert_eq!(find_cycle(&cyclic), Some(vec![1, 3, 2, 1]));
+    }
+
+    #[test]
+    fn render_dependency_tree() {
+        let tasks = vec![task(1, &[]), task(2, &[1]), task(3, &[1, 2]), task(4, &[])];
+        let summaries = HashMap::new();
+
+        let text = render_text(&tasks, &summaries);
+        let ids: Vec<_> = text
+            .lines()
+            .map(|l| l.split_at(l.find('<').unwrap()).0)
+            .collect();
+        assert_eq!(ids, vec!["001 ", "    002 ", "        003 ", "    003 "]);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::index::{Change, Status, Task};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+/// A status change, with how long the task then stayed in the new status
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub change: &'a Change,
+    pub spent: Duration,
+}
+
+/// The changes of a task in order. The time spent after the latest change runs until
+/// `now`.
+pub fn entries(task: &Task, now: DateTime<Utc>) -> Vec<Entry<'_>> {
+    task.changes
+        .iter()
+        .enumerate()
+        .map(|(i, change)| {
+            let until = task.changes.get(i + 1).map(|c| c.on).unwrap_or(now);
+            Entry {
+                change,
+                spent: until - change.on,
+            }
+        })
+        .collect()
+}
+
+/// The total time a task has spent in each status. Time spent in `Done` is not
+/// counted, as finished tasks stay there forever.
+pub fn time_in_status(task: &Task, now: DateTime<Utc>) -> BTreeMap<Status, Duration> {
+    let mut totals = BTreeMap::new();
+    for entry in entries(task, now) {
+        if entry.change.to == Status::Done {
+            continue;
+        }
+        let total = totals
+            .get(&entry.change.to)
+            .copied()
+            .unwrap_or_else(Duration::zero);
+        totals.insert(entry.change.to, total + entry.spent);
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn time_spent_in_each_status() {
+        let on = |hour| Utc.with_ymd_and_hms(2021, 9, 19, hour, 0, 0).unwrap();
+        let change = |from, to, hour| Change {
+            from,
+            to,
+            on: on(hour),
+            by: None,
+        };
+        let task = Task {
+            id: 1,
+            status: Status::Doing,
+            changes: vec![
+                change(Status::None, Status::Todo, 1),
+                change(Status::Todo, Status::Doing, 2),
+                change(Status::Doing, Status::Todo, 4),
+                change(Status::Todo, Status::Doing, 5),
+            ],
+            ..Default::default()
+        };
+
+        let totals = time_in_status(&task, on(8));
+        assert_eq!(totals.get(&Status::Todo), Some(&Duration::hours(2)));
+        assert_eq!(totals.get(&Status::Doing), Some(&Duration::hours(5)));
+    }
+}
@@ -1,17 +1,60 @@
-use chrono::{DateTime, Utc};
+use crate::archive;
+use crate::attachment;
+use crate::comment;
+use crate::dates;
+use crate::estimate::Estimate;
+use crate::git;
+use crate::graph;
+use crate::milestone::Milestone;
+use crate::sprint::{self, Sprint};
+use crate::storage::Storage;
+use crate::trash::{self, Trashed};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use eyre::{Result, WrapErr};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
-use std::io::Write;
-use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The path of the index file, relative to the board root
+pub const INDEX_FILE: &str = "index.yml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Meta {
     pub name: String,
+    /// The maximum number of tasks allowed in each status
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub wip_limits: BTreeMap<Status, usize>,
+    #[serde(default, skip_serializing_if = "WipPolicy::is_default")]
+    pub wip_policy: WipPolicy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub milestones: Vec<Milestone>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sprints: Vec<Sprint>,
+    /// Attachments larger than this many bytes trigger a warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment_warn_size: Option<u64>,
+}
+
+/// What to do when a task would exceed a work-in-progress limit
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum WipPolicy {
+    #[default]
+    Refuse,
+    Warn,
+}
+
+impl WipPolicy {
+    fn is_default(&self) -> bool {
+        *self == WipPolicy::default()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(
+    Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default,
+)]
 pub enum Status {
+    /// Not on the board, the status a task starts its history from
+    #[default]
     None,
     Todo,
     Doing,
@@ -47,46 +90,175 @@ pub struct Change {
     pub from: Status,
     pub to: Status,
     pub on: DateTime<Utc>,
+    /// Who made the change, as `Name <email>`. Missing from older histories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+}
+
+impl Change {
+    /// Whether the change was made by someone matching `who`, compared
+    /// case-insensitively against the name and email
+    pub fn made_by(&self, who: &str) -> bool {
+        match &self.by {
+            Some(by) => by.to_lowercase().contains(&who.to_lowercase()),
+            None => false,
+        }
+    }
 }
 
+/// A period of work on a task, recorded by `git pm timer`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkInterval {
+    pub start: DateTime<Utc>,
+    /// When the timer was stopped, or `None` while it is still running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+}
+
+impl WorkInterval {
+    /// How long the interval lasted, counting a running timer up to `now`
+    pub fn duration(&self, now: DateTime<Utc>) -> Duration {
+        self.end.unwrap_or(now) - self.start
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Task {
     pub id: u64,
     pub status: Status,
     pub changes: Vec<Change>,
     pub priority: Option<i64>,
+    /// The day the task should be finished by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    /// The day work on the task is planned to start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub work: Vec<WorkInterval>,
+    /// The tasks which must be finished before this one can start
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<u64>,
+    /// The task this is a subtask of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u64>,
+    /// The name of the milestone the task is part of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub milestone: Option<String>,
 }
 
 impl Task {
+    /// Whether the task is unfinished after its due date
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.status != Status::Done && self.due.map(|d| d < today).unwrap_or(false)
+    }
+
+    /// Read the task's detail file, from the archive or the trash if the task has been
+    /// archived or deleted
     pub fn detail(&self) -> Result<TaskDetail> {
-        let contents =
-            std::fs::read_to_string(self.target_path().wrap_err("computing target path")?)
-                .wrap_err("reading task detail")?;
-        let mut parts = contents.splitn(3, "---");
-        let _ = parts.next().unwrap();
-        let header: TaskDetailHeader =
-            serde_yaml::from_str(parts.next().unwrap()).wrap_err("parsing task detail")?;
-        let description = parts.next().unwrap();
-        Ok(TaskDetail {
-            id: header.id,
-            summary: header.summary,
-            tags: header.tags,
-            description: description.to_string(),
-        })
+        let storage = Storage::current()?;
+        let paths = [
+            detail_file(self.id),
+            archive::detail_file(self.id),
+            trash::detail_file(self.id),
+        ];
+        for path in &paths {
+            if let Some(contents) = storage
+                .read_to_string(path)
+                .wrap_err("reading task detail")?
+            {
+                return TaskDetail::parse(&contents);
+            }
+        }
+        Err(eyre::eyre!("could not find task detail {}", paths[0]))
+    }
+
+    pub fn set_date(&mut self, kind: DateKind, date: Option<NaiveDate>) {
+        match kind {
+            DateKind::Due => self.due = date,
+            DateKind::Scheduled => self.scheduled = date,
+        }
+    }
+
+    /// The total time logged against the task, or `None` if no work was logged
+    pub fn time_worked(&self, now: DateTime<Utc>) -> Option<Duration> {
+        if self.work.is_empty() {
+            return None;
+        }
+        Some(
+            self.work
+                .iter()
+                .fold(Duration::zero(), |acc, w| acc + w.duration(now)),
+        )
+    }
+
+    /// The timer `by` is running on the task, if any
+    pub fn running_timer(&self, by: &Option<String>) -> Option<&WorkInterval> {
+        self.work.iter().find(|w| w.end.is_none() && &w.by == by)
+    }
+
+    /// When the task was added to the board
+    pub fn created_on(&self) -> Option<DateTime<Utc>> {
+        self.changes.first().map(|c| c.on)
+    }
+
+    /// When the task last changed status
+    pub fn last_changed(&self) -> Option<DateTime<Utc>> {
+        self.changes.last().map(|c| c.on)
+    }
+
+    /// When work on the task first started
+    pub fn started_on(&self) -> Option<DateTime<Utc>> {
+        self.changes
+            .iter()
+            .find(|c| c.to == Status::Doing)
+            .map(|c| c.on)
     }
 
-    fn target_path(&self) -> Result<PathBuf> {
-        let pm_dir = find_project_root()
-            .map(|r| r.join("pm"))
-            .wrap_err("computing pm dir")?;
-        Ok(pm_dir.join("tasks").join(format!("{:03}.md", self.id)))
+    /// When the task was last moved to `Done`, if it ever was
+    pub fn finished_on(&self) -> Option<DateTime<Utc>> {
+        self.finishing_change().map(|c| c.on)
     }
+
+    /// The most recent move to `Done`
+    pub fn finishing_change(&self) -> Option<&Change> {
+        self.changes
+            .iter()
+            .filter(|c| c.to == Status::Done)
+            .max_by_key(|c| c.on)
+    }
+}
+
+/// How many of a number of items are complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}/{}", self.done, self.total)
+    }
+}
+
+/// The path of a task detail file, relative to the board root
+pub fn detail_file(task_id: u64) -> String {
+    format!("tasks/{:03}.md", task_id)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Index {
     pub meta: Meta,
     pub tasks: Vec<Task>,
+    /// Finished tasks moved out of the index by `archive`, kept in their own file
+    #[serde(skip)]
+    pub archived: Vec<Task>,
+    /// Deleted tasks, kept in their own file until the trash is purged
+    #[serde(skip)]
+    pub trashed: Vec<Trashed>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -94,6 +266,12 @@ struct TaskDetailHeader {
     id: u64,
     summary: String,
     tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    assignees: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate: Option<Estimate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -102,42 +280,147 @@ pub struct TaskDetail {
     pub summary: String,
     pub description: String,
     pub tags: Vec<String>,
+    pub assignees: Vec<String>,
+    pub estimate: Option<Estimate>,
+    /// The file names of the task's attachments
+    pub attachments: Vec<String>,
+}
+
+/// Whether a word of a new task entry is a `:tag:`
+fn is_tag(word: &str) -> bool {
+    word.len() > 1 && word.starts_with(':') && word.ends_with(':')
+}
+
+/// Whether a word of a new task entry is an `@name` assignee
+fn is_assignee(word: &str) -> bool {
+    word.len() > 1 && word.starts_with('@')
+}
+
+/// Split a `due:<day>` or `scheduled:<day>` word of a new task entry into its kind
+/// and day
+fn date_token(word: &str) -> Option<(DateKind, &str)> {
+    let (kind, day) = word.split_once(':')?;
+    if day.is_empty() {
+        return None;
+    }
+    match kind {
+        "due" => Some((DateKind::Due, day)),
+        "scheduled" => Some((DateKind::Scheduled, day)),
+        _ => None,
+    }
 }
 
 impl TaskDetail {
+    /// Parse the contents of a task detail file: a yaml header between `---` markers,
+    /// followed by the markdown description.
+    pub fn parse(contents: &str) -> Result<TaskDetail> {
+        let mut parts = contents.splitn(3, "---");
+        let _ = parts.next();
+        let header = parts
+            .next()
+            .ok_or_else(|| eyre::eyre!("task detail is missing its header"))?;
+        let header: TaskDetailHeader =
+            serde_yaml::from_str(header).wrap_err("parsing task detail")?;
+        let description = parts.next().unwrap_or("");
+        Ok(TaskDetail {
+            id: header.id,
+            summary: header.summary,
+            tags: header.tags,
+            assignees: header.assignees,
+            estimate: header.estimate,
+            attachments: header.attachments,
+            description: description.to_string(),
+        })
+    }
+
     fn new(task_id: u64, entry: &[String]) -> TaskDetail {
         let summary_entries = entry
             .iter()
-            .filter(|w| !(w.starts_with(':') && w.ends_with(':')))
+            .filter(|w| !is_tag(w) && !is_assignee(w) && date_token(w).is_none())
             .map(|w| w.as_str())
             .collect::<Vec<_>>();
         let summary = summary_entries.join(" ");
         let tags = entry
             .iter()
             .filter_map(|e| {
-                if e.starts_with(':') && e.ends_with(':') {
+                if is_tag(e) {
                     Some(e.chars().skip(1).take_while(|c| *c != ':').collect())
                 } else {
                     None
                 }
             })
             .collect();
+        let assignees = entry
+            .iter()
+            .filter(|e| is_assignee(e))
+            .map(|e| e[1..].to_string())
+            .collect();
         TaskDetail {
             id: task_id,
             summary,
             description: "".to_string(),
             tags,
+            assignees,
+            estimate: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Add `who` to the assignees, returning false if they were already assigned
+    pub fn assign(&mut self, who: &str) -> bool {
+        if self.assignees.iter().any(|a| a.eq_ignore_ascii_case(who)) {
+            return false;
+        }
+        self.assignees.push(who.to_string());
+        true
+    }
+
+    /// Remove `who` from the assignees, returning false if they were not assigned
+    pub fn unassign(&mut self, who: &str) -> bool {
+        let before = self.assignees.len();
+        self.assignees.retain(|a| !a.eq_ignore_ascii_case(who));
+        self.assignees.len() != before
+    }
+
+    /// Whether the task is assigned to any of the given names, compared
+    /// case-insensitively
+    pub fn is_assigned_to(&self, names: &[String]) -> bool {
+        self.assignees
+            .iter()
+            .any(|a| names.iter().any(|n| a.eq_ignore_ascii_case(n)))
+    }
+
+    /// Count the Markdown checklist items (`- [ ]` and `- [x]`) in the description, or
+    /// `None` if it has none
+    pub fn checklist(&self) -> Option<Progress> {
+        let mut progress = Progress { done: 0, total: 0 };
+        for line in self.description.lines() {
+            let line = line.trim_start();
+            let item = match line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+                Some(item) => item,
+                None => continue,
+            };
+            if item.starts_with("[ ]") {
+                progress.total += 1;
+            } else if item.starts_with("[x]") || item.starts_with("[X]") {
+                progress.total += 1;
+                progress.done += 1;
+            }
+        }
+        if progress.total == 0 {
+            None
+        } else {
+            Some(progress)
         }
     }
 
-    fn save(&self) -> Result<()> {
-        let path = self.target_path().wrap_err("finding detail path")?;
+    pub fn save(&self) -> Result<()> {
         let header = self.header();
         let header = serde_yaml::to_string(&header).wrap_err("serializing task detail")?;
-        let mut f = std::fs::File::create(path).wrap_err("creating file")?;
-        writeln!(&mut f, "{}", header)?;
-        writeln!(&mut f, "---")?;
-        writeln!(&mut f, "{}", self.description.trim())?;
+        let contents = format!("{}\n---\n{}\n", header, self.description.trim());
+        Storage::current()?
+            .write(&detail_file(self.id), contents.as_bytes())
+            .wrap_err("writing task detail")?;
         Ok(())
     }
 
@@ -146,18 +429,11 @@ impl TaskDetail {
             id: self.id,
             summary: self.summary.clone(),
             tags: self.tags.clone(),
+            assignees: self.assignees.clone(),
+            estimate: self.estimate,
+            attachments: self.attachments.clone(),
         }
     }
-
-    fn target_path(&self) -> Result<PathBuf> {
-        let pm_dir = find_project_root()
-            .map(|r| r.join("pm"))
-            .wrap_err("computing pm dir")?;
-        let tasks_dir = pm_dir.join("tasks");
-        std::fs::create_dir_all(&tasks_dir).wrap_err("creating tasks dir")?;
-        let filename = format!("{:03}.md", self.id);
-        Ok(tasks_dir.join(filename))
-    }
 }
 
 pub enum Priority {
@@ -165,120 +441,329 @@ pub enum Priority {
     Decrease,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateKind {
+    Due,
+    Scheduled,
+}
+
+impl fmt::Display for DateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            DateKind::Due => write!(f, "due"),
+            DateKind::Scheduled => write!(f, "scheduled"),
+        }
+    }
+}
+
 impl Index {
     pub fn new(name: impl Into<String>) -> Result<Index> {
         Ok(Index {
-            meta: Meta { name: name.into() },
+            meta: Meta {
+                name: name.into(),
+                ..Default::default()
+            },
             tasks: Vec::new(),
+            archived: Vec::new(),
+            trashed: Vec::new(),
         })
     }
 
     pub fn save(&self, force: bool) -> Result<()> {
-        let path = find_index_path().wrap_err("finding index path")?;
-        if path.is_file() && !force {
+        let storage = Storage::current()?;
+        if storage.exists(INDEX_FILE)? && !force {
             return Err(crate::error::PmError::IndexExists.into());
         }
-        ensure_parent_dir(&path)
-            .wrap_err_with(|| format!("ensuring parent dir for path {:?}", path))?;
         let body = serde_yaml::to_string(self).wrap_err("serializing index")?;
-        std::fs::write(path, body).wrap_err("writing index")?;
+        storage
+            .write(INDEX_FILE, body.as_bytes())
+            .wrap_err("writing index")?;
         Ok(())
     }
 
     pub fn load() -> Result<Index> {
-        let path = find_index_path().wrap_err("finding index path")?;
-        let contents = std::fs::read_to_string(&path)
-            .wrap_err_with(|| format!("reading config file {:?}", &path))?;
-        let index: Index = serde_yaml::from_str(&contents).wrap_err("parsing index")?;
+        let storage = Storage::current()?;
+        let contents = storage
+            .read_to_string(INDEX_FILE)
+            .wrap_err_with(|| format!("reading index from {}", storage))?
+            .ok_or_else(|| eyre::eyre!("could not find index, run `git pm init` first"))?;
+        let mut index = Index::parse(&contents)?;
+        index.archived = archive::load().wrap_err("loading archive")?;
+        index.trashed = trash::load().wrap_err("loading trash")?;
         Ok(index)
     }
 
-    pub fn create_task(&mut self, entry: &[String]) -> Result<()> {
-        let task = Task {
+    pub fn parse(contents: &str) -> Result<Index> {
+        let index: Index = serde_yaml::from_str(contents).wrap_err("parsing index")?;
+        Ok(index)
+    }
+
+    /// Add a task to the backlog, returning its id
+    pub fn create_task(&mut self, entry: &[String], parent: Option<u64>) -> Result<u64> {
+        if let Some(parent) = parent {
+            if self.get_task(parent).is_none() {
+                return Err(eyre::eyre!("could not find parent task {}", parent));
+            }
+        }
+        self.check_wip_limit(Status::Todo)?;
+        let mut task = Task {
             id: self.next_id(),
             status: Status::Todo,
             changes: vec![Change {
                 from: Status::None,
                 to: Status::Todo,
                 on: Utc::now(),
+                by: git::user_ident()?,
             }],
-            priority: None,
+            parent,
+            ..Default::default()
         };
+        let today = Utc::now().naive_utc().date();
+        for (kind, day) in entry.iter().filter_map(|w| date_token(w)) {
+            let date = dates::parse_day(day, today)
+                .ok_or_else(|| eyre::eyre!("invalid {} date {}", kind, day))?;
+            task.set_date(kind, Some(date));
+        }
 
-        let detail = TaskDetail::new(task.id, entry);
+        let id = task.id;
+        let detail = TaskDetail::new(id, entry);
 
         self.tasks.push(task);
         // TODO(srw): handle the case of one file not saving and rolling back
         self.save(true).wrap_err("saving")?;
         detail.save().wrap_err("saving task detail")?;
 
-        Ok(())
+        Ok(id)
     }
 
     pub fn get_task(&self, task_id: u64) -> Option<&Task> {
-        for task in &self.tasks {
-            if task.id == task_id {
-                return Some(task);
-            }
-        }
+        self.tasks.iter().find(|t| t.id == task_id)
+    }
 
-        None
+    /// Find a task on the board or in the archive
+    pub fn find_task(&self, task_id: u64) -> Option<&Task> {
+        self.get_task(task_id)
+            .or_else(|| self.archived.iter().find(|t| t.id == task_id))
     }
 
-    pub fn move_task(&mut self, task_id: u64, new_status: Status) -> Result<()> {
-        let mut found = false;
-        for task in self.tasks.iter_mut() {
-            if task.id == task_id {
-                found = true;
+    pub fn is_archived(&self, task_id: u64) -> bool {
+        self.archived.iter().any(|t| t.id == task_id)
+    }
 
-                if task.status == new_status {
-                    // do not update
-                    break;
-                }
+    /// The tasks on the board followed by the archived tasks, for reports covering
+    /// the whole history of the project
+    pub fn all_tasks(&self) -> Vec<Task> {
+        self.tasks.iter().chain(&self.archived).cloned().collect()
+    }
 
-                let change = Change {
-                    from: task.status,
-                    to: new_status,
-                    on: Utc::now(),
-                };
-                task.changes.push(change);
-                task.status = new_status;
-                break;
-            }
+    /// Move tasks finished before `done_before` into the archive, returning their ids
+    pub fn archive(&mut self, done_before: NaiveDate) -> Result<Vec<u64>> {
+        let ids = self.move_to_archive(done_before);
+        let storage = Storage::current()?;
+        for id in &ids {
+            storage
+                .rename(&detail_file(*id), &archive::detail_file(*id))
+                .wrap_err_with(|| format!("archiving task detail {}", id))?;
         }
+        archive::save(&self.archived).wrap_err("saving archive")?;
+        self.save(true).wrap_err("saving")?;
+        Ok(ids)
+    }
 
-        if !found {
-            return Err(eyre::eyre!("could not find task {}", task_id));
+    /// Move an archived task back onto the board
+    pub fn unarchive(&mut self, task_id: u64) -> Result<()> {
+        self.move_from_archive(task_id)?;
+        Storage::current()?
+            .rename(&archive::detail_file(task_id), &detail_file(task_id))
+            .wrap_err("restoring task detail")?;
+        archive::save(&self.archived).wrap_err("saving archive")?;
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// Move the tasks finished before `done_before` from `tasks` to `archived`,
+    /// returning their ids
+    fn move_to_archive(&mut self, done_before: NaiveDate) -> Vec<u64> {
+        let (archived, kept): (Vec<Task>, Vec<Task>) =
+            std::mem::take(&mut self.tasks).into_iter().partition(|t| {
+                t.status == Status::Done
+                    && t.finished_on()
+                        .map(|on| on.naive_utc().date() < done_before)
+                        .unwrap_or(false)
+            });
+        self.tasks = kept;
+        let ids = archived.iter().map(|t| t.id).collect();
+        self.archived.extend(archived);
+        self.archived.sort_by_key(|t| t.id);
+        ids
+    }
+
+    fn move_from_archive(&mut self, task_id: u64) -> Result<()> {
+        let idx = self
+            .archived
+            .iter()
+            .position(|t| t.id == task_id)
+            .ok_or_else(|| eyre::eyre!("task {} is not archived", task_id))?;
+        let task = self.archived.remove(idx);
+        self.tasks.push(task);
+        self.tasks.sort_by_key(|t| t.id);
+        Ok(())
+    }
+
+    pub fn move_task(&mut self, task_id: u64, new_status: Status) -> Result<()> {
+        let idx = self
+            .tasks
+            .iter()
+            .position(|t| t.id == task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        if self.tasks[idx].status == new_status {
+            // do not update
+            return Ok(());
         }
+        self.check_wip_limit(new_status)?;
+
+        let task = &mut self.tasks[idx];
+        let change = Change {
+            from: task.status,
+            to: new_status,
+            on: Utc::now(),
+            by: git::user_ident()?,
+        };
+        task.changes.push(change);
+        task.status = new_status;
 
         self.save(true).wrap_err("saving")?;
         Ok(())
     }
 
+    /// Delete a task for good, along with its comments and attachments, whether it is
+    /// on the board, archived or in the trash
     pub fn delete_task(&mut self, task_id: u64) -> Result<()> {
-        let detail_path = self.detail_path(task_id).wrap_err("getting detail path")?;
-        std::fs::remove_file(&detail_path)
-            .wrap_err_with(|| format!("deleting file {:?}", &detail_path))?;
-        if let Some(idx) = self.tasks.iter().position(|t| t.id == task_id) {
+        let path = if let Some(idx) = self.tasks.iter().position(|t| t.id == task_id) {
             self.tasks.remove(idx);
+            detail_file(task_id)
+        } else if let Some(idx) = self.archived.iter().position(|t| t.id == task_id) {
+            self.archived.remove(idx);
+            archive::save(&self.archived).wrap_err("saving archive")?;
+            archive::detail_file(task_id)
+        } else if let Some(idx) = self.trashed.iter().position(|t| t.task.id == task_id) {
+            self.trashed.remove(idx);
+            trash::save(&self.trashed).wrap_err("saving trash")?;
+            trash::detail_file(task_id)
+        } else {
+            return Err(eyre::eyre!("could not find task {}", task_id));
+        };
+        attachment::remove_all(task_id)?;
+        let storage = Storage::current()?;
+        if storage.exists(&path)? {
+            storage.remove(&path).wrap_err("deleting task detail")?;
+        }
+        comment::remove(task_id)?;
+        self.unlink(task_id);
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// Drop references to a task from the others, returning the tasks it was blocking
+    /// and its subtasks
+    fn unlink(&mut self, task_id: u64) -> (Vec<u64>, Vec<u64>) {
+        let mut blocked = Vec::new();
+        let mut children = Vec::new();
+        for task in self.tasks.iter_mut() {
+            if task.blocked_by.contains(&task_id) {
+                task.blocked_by.retain(|b| *b != task_id);
+                blocked.push(task.id);
+            }
+            if task.parent == Some(task_id) {
+                task.parent = None;
+                children.push(task.id);
+            }
+        }
+        (blocked, children)
+    }
+
+    /// Move a task into the trash, from where it can be restored until the trash is
+    /// purged. Its comments and attachments stay where they are.
+    pub fn trash_task(&mut self, task_id: u64) -> Result<()> {
+        let idx = self
+            .tasks
+            .iter()
+            .position(|t| t.id == task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        Storage::current()?
+            .rename(&detail_file(task_id), &trash::detail_file(task_id))
+            .wrap_err("moving task detail to the trash")?;
+        let task = self.tasks.remove(idx);
+        let (blocked, children) = self.unlink(task_id);
+        self.trashed.push(Trashed {
+            task,
+            deleted_on: Utc::now(),
+            deleted_by: git::user_ident()?,
+            blocked,
+            children,
+        });
+        trash::save(&self.trashed).wrap_err("saving trash")?;
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// Move a task out of the trash and back onto the board, relinking it to the tasks
+    /// it was blocking and its subtasks where they still exist
+    pub fn restore_task(&mut self, task_id: u64) -> Result<()> {
+        let idx = self
+            .trashed
+            .iter()
+            .position(|t| t.task.id == task_id)
+            .ok_or_else(|| eyre::eyre!("task {} is not in the trash", task_id))?;
+        Storage::current()?
+            .rename(&trash::detail_file(task_id), &detail_file(task_id))
+            .wrap_err("restoring task detail")?;
+        let trashed = self.trashed.remove(idx);
+        for task in self.tasks.iter_mut() {
+            if trashed.blocked.contains(&task.id) && !task.blocked_by.contains(&task_id) {
+                task.blocked_by.push(task_id);
+            }
+            if trashed.children.contains(&task.id) && task.parent.is_none() {
+                task.parent = Some(task_id);
+            }
         }
+        self.tasks.push(trashed.task);
+        self.tasks.sort_by_key(|t| t.id);
+        trash::save(&self.trashed).wrap_err("saving trash")?;
         self.save(true).wrap_err("saving")?;
         Ok(())
     }
 
-    pub fn detail_path(&self, task_id: u64) -> Result<PathBuf> {
-        let pm_dir = find_project_root()
-            .map(|r| r.join("pm"))
-            .wrap_err("computing pm dir")?;
-        Ok(pm_dir.join("tasks").join(format!("{:03}.md", task_id)))
+    /// Delete the tasks which have been in the trash for longer than `age` for good,
+    /// returning their ids
+    pub fn purge_trash(&mut self, age: Duration, now: DateTime<Utc>) -> Result<Vec<u64>> {
+        let (expired, kept): (Vec<Trashed>, Vec<Trashed>) = std::mem::take(&mut self.trashed)
+            .into_iter()
+            .partition(|t| t.is_older_than(age, now));
+        self.trashed = kept;
+        let storage = Storage::current()?;
+        for trashed in &expired {
+            let id = trashed.task.id;
+            attachment::remove_all(id)?;
+            storage
+                .remove(&trash::detail_file(id))
+                .wrap_err("deleting task detail")?;
+            comment::remove(id)?;
+        }
+        trash::save(&self.trashed).wrap_err("saving trash")?;
+        Ok(expired.iter().map(|t| t.task.id).collect())
+    }
+
+    pub fn is_trashed(&self, task_id: u64) -> bool {
+        self.trashed.iter().any(|t| t.task.id == task_id)
     }
 
     pub fn sorted_tasks_with_status(&self, status: Status) -> Option<Vec<Task>> {
         let mut tasks: Vec<_> = self
             .tasks
             .iter()
-            .cloned()
             .filter(|t| t.status == status)
+            .cloned()
             .collect();
         if tasks.is_empty() {
             return None;
@@ -295,7 +780,7 @@ impl Index {
     }
 
     pub fn update_task_priority(&mut self, task_id: u64, priority: Priority) -> Result<()> {
-        match self.tasks.iter_mut().filter(|t| t.id == task_id).next() {
+        match self.tasks.iter_mut().find(|t| t.id == task_id) {
             Some(task) => match priority {
                 Priority::Increase => task.priority = Some(task.priority.unwrap_or(0) + 1),
                 Priority::Decrease => task.priority = Some(task.priority.unwrap_or(0) - 1),
@@ -307,35 +792,342 @@ impl Index {
         Ok(())
     }
 
-    fn next_id(&self) -> u64 {
-        self.tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1
+    pub fn update_task_date(
+        &mut self,
+        task_id: u64,
+        kind: DateKind,
+        date: Option<NaiveDate>,
+    ) -> Result<()> {
+        match self.tasks.iter_mut().find(|t| t.id == task_id) {
+            Some(task) => task.set_date(kind, date),
+            None => return Err(eyre::eyre!("could not find task {}", task_id)),
+        }
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
     }
-}
 
-fn find_index_path() -> Result<PathBuf> {
-    let project_root = find_project_root().wrap_err("finding project root")?;
-    Ok(project_root.join("pm").join("index.yml"))
-}
+    /// Start a timer on a task for the current user
+    pub fn start_timer(&mut self, task_id: u64) -> Result<()> {
+        let by = git::user_ident()?;
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        if let Some(running) = task.running_timer(&by) {
+            return Err(eyre::eyre!(
+                "a timer has been running on task {} since {}",
+                task_id,
+                running.start.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        task.work.push(WorkInterval {
+            start: Utc::now(),
+            end: None,
+            by,
+        });
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// Stop the current user's timer on a task, returning how long it ran
+    pub fn stop_timer(&mut self, task_id: u64) -> Result<Duration> {
+        let by = git::user_ident()?;
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        let now = Utc::now();
+        let interval = task
+            .work
+            .iter_mut()
+            .find(|w| w.end.is_none() && w.by == by)
+            .ok_or_else(|| eyre::eyre!("no timer is running on task {}", task_id))?;
+        interval.end = Some(now);
+        let duration = interval.duration(now);
+
+        self.save(true).wrap_err("saving")?;
+        Ok(duration)
+    }
 
-fn find_project_root() -> Result<PathBuf> {
-    let mut cwd = std::env::current_dir().wrap_err("getting current dir")?;
-    loop {
-        if cwd == Path::new("/") {
-            return Err(eyre::eyre!("could not find root path for git repository"));
+    /// Record that `task_id` cannot start until `blocker` is finished, refusing
+    /// dependencies which would form a cycle
+    pub fn add_dependency(&mut self, task_id: u64, blocker: u64) -> Result<()> {
+        if task_id == blocker {
+            return Err(eyre::eyre!("a task cannot depend on itself"));
+        }
+        for id in &[task_id, blocker] {
+            if self.get_task(*id).is_none() {
+                return Err(eyre::eyre!("could not find task {}", id));
+            }
         }
-        if cwd.join(".git").is_dir() {
-            return Ok(cwd);
+        if let Some(path) = graph::dependency_path(&self.tasks, blocker, task_id) {
+            let path: Vec<_> = path.iter().map(|id| id.to_string()).collect();
+            return Err(eyre::eyre!(
+                "task {} already depends on task {} ({}), refusing to create a cycle",
+                blocker,
+                task_id,
+                path.join(" -> ")
+            ));
+        }
+
+        // unwrap is safe because the task was found above
+        let task = self.tasks.iter_mut().find(|t| t.id == task_id).unwrap();
+        if !task.blocked_by.contains(&blocker) {
+            task.blocked_by.push(blocker);
+            task.blocked_by.sort_unstable();
+        }
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    pub fn remove_dependency(&mut self, task_id: u64, blocker: u64) -> Result<()> {
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        if !task.blocked_by.contains(&blocker) {
+            return Err(eyre::eyre!(
+                "task {} does not depend on task {}",
+                task_id,
+                blocker
+            ));
+        }
+        task.blocked_by.retain(|b| *b != blocker);
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    pub fn milestone(&self, name: &str) -> Option<&Milestone> {
+        self.meta.milestones.iter().find(|m| m.name == name)
+    }
+
+    pub fn add_milestone(&mut self, milestone: Milestone) -> Result<()> {
+        if self.milestone(&milestone.name).is_some() {
+            return Err(eyre::eyre!("milestone {} already exists", milestone.name));
+        }
+        self.meta.milestones.push(milestone);
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// Remove a milestone, taking its tasks out of it
+    pub fn remove_milestone(&mut self, name: &str) -> Result<()> {
+        if self.milestone(name).is_none() {
+            return Err(eyre::eyre!("could not find milestone {}", name));
+        }
+        self.meta.milestones.retain(|m| m.name != name);
+        for task in self.tasks.iter_mut() {
+            if task.milestone.as_deref() == Some(name) {
+                task.milestone = None;
+            }
+        }
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    pub fn update_task_milestone(&mut self, task_id: u64, name: Option<String>) -> Result<()> {
+        if let Some(name) = &name {
+            if self.milestone(name).is_none() {
+                return Err(eyre::eyre!("could not find milestone {}", name));
+            }
+        }
+        match self.tasks.iter_mut().find(|t| t.id == task_id) {
+            Some(task) => task.milestone = name,
+            None => return Err(eyre::eyre!("could not find task {}", task_id)),
+        }
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// The sprint currently open, if any
+    pub fn current_sprint(&self) -> Option<&Sprint> {
+        self.meta.sprints.iter().rev().find(|s| !s.closed)
+    }
+
+    pub fn sprint(&self, name: &str) -> Option<&Sprint> {
+        self.meta.sprints.iter().find(|s| s.name == name)
+    }
+
+    /// Open a new sprint, refusing while another sprint is still open
+    pub fn add_sprint(&mut self, sprint: Sprint) -> Result<()> {
+        if let Some(current) = self.current_sprint() {
+            return Err(eyre::eyre!(
+                "{} is still open, close it first",
+                current.name
+            ));
+        }
+        if self.sprint(&sprint.name).is_some() {
+            return Err(eyre::eyre!("sprint {} already exists", sprint.name));
+        }
+        self.meta.sprints.push(sprint);
+
+        self.save(true).wrap_err("saving")?;
+        Ok(())
+    }
+
+    /// Pull tasks into the current sprint, or every `Todo` task not yet in it if no
+    /// tasks are given. Returns the tasks added.
+    pub fn plan_sprint(&mut self, task_ids: &[u64]) -> Result<Vec<u64>> {
+        let task_ids: Vec<u64> = if task_ids.is_empty() {
+            self.tasks
+                .iter()
+                .filter(|t| t.status == Status::Todo)
+                .map(|t| t.id)
+                .collect()
+        } else {
+            for id in task_ids {
+                match self.get_task(*id) {
+                    Some(task) if task.status == Status::Done => {
+                        return Err(eyre::eyre!("task {} is already done", id))
+                    }
+                    Some(_) => {}
+                    None => return Err(eyre::eyre!("could not find task {}", id)),
+                }
+            }
+            task_ids.to_vec()
+        };
+
+        let sprint = self
+            .meta
+            .sprints
+            .iter_mut()
+            .rev()
+            .find(|s| !s.closed)
+            .ok_or_else(|| eyre::eyre!("no sprint is open, start one first"))?;
+        let added: Vec<u64> = task_ids
+            .into_iter()
+            .filter(|id| !sprint.tasks.contains(id))
+            .collect();
+        sprint.tasks.extend(&added);
+
+        self.save(true).wrap_err("saving")?;
+        Ok(added)
+    }
+
+    /// Close the current sprint and open the next one, rolling over the unfinished
+    /// tasks. Returns the new sprint.
+    pub fn close_sprint(&mut self, today: NaiveDate) -> Result<&Sprint> {
+        let name = sprint::next_name(&self.meta.sprints);
+        let current = self
+            .current_sprint()
+            .ok_or_else(|| eyre::eyre!("no sprint is open"))?;
+        let mut next = current.next(name, today);
+        next.tasks = current
+            .tasks
+            .iter()
+            .filter(|id| matches!(self.get_task(**id), Some(t) if t.status != Status::Done))
+            .copied()
+            .collect();
+
+        // unwrap is safe because there is an open sprint
+        let current = self
+            .meta
+            .sprints
+            .iter_mut()
+            .rev()
+            .find(|s| !s.closed)
+            .unwrap();
+        current.closed = true;
+        self.meta.sprints.push(next);
+
+        self.save(true).wrap_err("saving")?;
+        // unwrap is safe because a sprint was just pushed
+        Ok(self.meta.sprints.last().unwrap())
+    }
+
+    /// The subtasks of a task, by id
+    pub fn children(&self, task_id: u64) -> Vec<&Task> {
+        let mut children: Vec<_> = self
+            .tasks
+            .iter()
+            .filter(|t| t.parent == Some(task_id))
+            .collect();
+        children.sort_by_key(|t| t.id);
+        children
+    }
+
+    /// How many subtasks of a task are `Done`, or `None` if it has no subtasks
+    pub fn subtask_progress(&self, task_id: u64) -> Option<Progress> {
+        let children = self.children(task_id);
+        if children.is_empty() {
+            return None;
+        }
+        Some(Progress {
+            done: children.iter().filter(|t| t.status == Status::Done).count(),
+            total: children.len(),
+        })
+    }
+
+    /// The blockers of a task which are not yet `Done`
+    pub fn unfinished_blockers(&self, task_id: u64) -> Vec<u64> {
+        self.get_task(task_id)
+            .map(|task| {
+                task.blocked_by
+                    .iter()
+                    .filter(|b| self.find_task(**b).map(|t| t.status) != Some(Status::Done))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Refuse to start a task while any of its blockers are unfinished
+    pub fn check_blockers(&self, task_id: u64) -> Result<()> {
+        let blockers = self.unfinished_blockers(task_id);
+        if blockers.is_empty() {
+            return Ok(());
+        }
+        Err(crate::error::PmError::Blocked { task_id, blockers }.into())
+    }
+
+    pub fn count_with_status(&self, status: Status) -> usize {
+        self.tasks.iter().filter(|t| t.status == status).count()
+    }
+
+    /// Check whether one more task fits into `status`, either refusing or warning
+    /// depending on the configured policy
+    fn check_wip_limit(&self, status: Status) -> Result<()> {
+        let limit = match self.meta.wip_limits.get(&status) {
+            Some(limit) => *limit,
+            None => return Ok(()),
+        };
+        if self.count_with_status(status) < limit {
+            return Ok(());
+        }
+        match self.meta.wip_policy {
+            WipPolicy::Refuse => {
+                Err(crate::error::PmError::WipLimitExceeded { status, limit }.into())
+            }
+            WipPolicy::Warn => {
+                eprintln!(
+                    "warning: {} now exceeds its limit of {} tasks",
+                    status, limit
+                );
+                Ok(())
+            }
         }
-        cwd = cwd.join("..").canonicalize()?;
     }
-}
 
-fn ensure_parent_dir(p: &Path) -> Result<()> {
-    // unwrap is safe because we construct the final two path components
-    let parent_dir = p.parent().unwrap();
-    std::fs::create_dir_all(parent_dir)
-        .wrap_err_with(|| format!("creating directory {:?}", parent_dir))?;
-    Ok(())
+    fn next_id(&self) -> u64 {
+        self.tasks
+            .iter()
+            .chain(&self.archived)
+            .chain(self.trashed.iter().map(|t| &t.task))
+            .map(|t| t.id)
+            .max()
+            .unwrap_or(0)
+            + 1
+    }
 }
 
 #[cfg(test)]
@@ -367,6 +1159,22 @@ tasks:
 
         let parsed: Index = serde_yaml::from_str(text).unwrap();
         assert_eq!(parsed.meta.name, "My first project");
+        assert_eq!(parsed.tasks[0].changes[0].by, None);
+    }
+
+    #[test]
+    fn parse_change_with_author() {
+        let text = r#"
+from: Todo
+to: Doing
+on: 2021-01-01T00:00:00+00:00
+by: Jane Doe <jane@example.com>
+"#;
+
+        let change: Change = serde_yaml::from_str(text).unwrap();
+        assert!(change.made_by("jane@example.com"));
+        assert!(change.made_by("jane doe"));
+        assert!(!change.made_by("john"));
     }
 
     #[test]
@@ -392,28 +1200,248 @@ tasks:
         assert_eq!(task_detail.tags, vec!["tag".to_string()]);
     }
 
+    #[test]
+    fn parse_entry_for_task_detail_with_assignees() {
+        let entry = vec![
+            "Fix".to_string(),
+            "@alice".to_string(),
+            "login".to_string(),
+            ":bug:".to_string(),
+            "@".to_string(),
+        ];
+        let mut task_detail = TaskDetail::new(0, &entry);
+
+        assert_eq!(task_detail.summary, "Fix login @".to_string());
+        assert_eq!(task_detail.assignees, vec!["alice".to_string()]);
+        assert!(task_detail.is_assigned_to(&["Alice".to_string()]));
+        assert!(!task_detail.assign("ALICE"));
+        assert!(task_detail.assign("bob"));
+        assert!(task_detail.unassign("alice"));
+        assert_eq!(task_detail.assignees, vec!["bob".to_string()]);
+    }
+
+    #[test]
+    fn parse_entry_with_dates() {
+        let entry = vec![
+            "Release".to_string(),
+            "due:2021-11-01".to_string(),
+            "scheduled:friday".to_string(),
+            "notes:".to_string(),
+        ];
+        let task_detail = TaskDetail::new(0, &entry);
+
+        assert_eq!(task_detail.summary, "Release notes:".to_string());
+        assert_eq!(
+            date_token("due:2021-11-01"),
+            Some((DateKind::Due, "2021-11-01"))
+        );
+        assert_eq!(date_token("notes:"), None);
+    }
+
+    #[test]
+    fn overdue_tasks() {
+        let mut task = Task {
+            id: 1,
+            status: Status::Doing,
+            due: Some(NaiveDate::from_ymd_opt(2021, 9, 19).unwrap()),
+            ..Default::default()
+        };
+        assert!(!task.is_overdue(NaiveDate::from_ymd_opt(2021, 9, 19).unwrap()));
+        assert!(task.is_overdue(NaiveDate::from_ymd_opt(2021, 9, 20).unwrap()));
+        task.status = Status::Done;
+        assert!(!task.is_overdue(NaiveDate::from_ymd_opt(2021, 9, 20).unwrap()));
+    }
+
+    #[test]
+    fn time_worked_includes_running_timers() {
+        let start = Utc::now() - Duration::hours(3);
+        let task = Task {
+            id: 1,
+            status: Status::Doing,
+            work: vec![
+                WorkInterval {
+                    start,
+                    end: Some(start + Duration::hours(1)),
+                    by: None,
+                },
+                WorkInterval {
+                    start: start + Duration::hours(2),
+                    end: None,
+                    by: Some("Jane Doe <jane@example.com>".to_string()),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let now = start + Duration::hours(3);
+        assert_eq!(task.time_worked(now), Some(Duration::hours(2)));
+        assert!(task.running_timer(&None).is_none());
+        assert!(task
+            .running_timer(&Some("Jane Doe <jane@example.com>".to_string()))
+            .is_some());
+    }
+
+    #[test]
+    fn checklist_progress() {
+        let mut task_detail = TaskDetail::new(0, &["Release".to_string()]);
+        assert_eq!(task_detail.checklist(), None);
+
+        task_detail.description = r#"
+Steps:
+- [x] tag the release
+- [ ] publish the crate
+  * [X] nested item
+- not a checklist item
+"#
+        .to_string();
+        assert_eq!(
+            task_detail.checklist(),
+            Some(Progress { done: 2, total: 3 })
+        );
+    }
+
+    #[test]
+    fn parse_index_with_wip_limits() {
+        let text = r#"
+meta:
+  name: My first project
+  wip_limits:
+    Doing: 2
+  wip_policy: Warn
+tasks: []
+"#;
+
+        let parsed: Index = serde_yaml::from_str(text).unwrap();
+        assert_eq!(parsed.meta.wip_limits.get(&Status::Doing), Some(&2));
+        assert_eq!(parsed.meta.wip_policy, WipPolicy::Warn);
+        assert!(serde_yaml::to_string(&parsed)
+            .unwrap()
+            .contains("wip_policy"));
+
+        let index = Index::new("Foo").unwrap();
+        assert!(!serde_yaml::to_string(&index)
+            .unwrap()
+            .contains("wip_policy"));
+    }
+
+    #[test]
+    fn wip_limit_refuses_move() {
+        let mut meta = Meta {
+            name: "Foo".to_string(),
+            ..Default::default()
+        };
+        meta.wip_limits.insert(Status::Doing, 1);
+        let index = Index {
+            meta,
+            tasks: vec![Task {
+                id: 1,
+                status: Status::Doing,
+                ..Default::default()
+            }],
+            archived: vec![],
+            trashed: vec![],
+        };
+
+        assert!(index.check_wip_limit(Status::Doing).is_err());
+        assert!(index.check_wip_limit(Status::Todo).is_ok());
+    }
+
+    #[test]
+    fn subtask_progress_rolls_up() {
+        let task = |id, status, parent| Task {
+            id,
+            status,
+            parent,
+            ..Default::default()
+        };
+        let index = Index {
+            meta: Meta {
+                name: "Foo".to_string(),
+                ..Default::default()
+            },
+            tasks: vec![
+                task(1, Status::Doing, None),
+                task(2, Status::Done, Some(1)),
+                task(3, Status::Todo, Some(1)),
+            ],
+            archived: vec![],
+            trashed: vec![],
+        };
+
+        assert_eq!(
+            index.subtask_progress(1),
+            Some(Progress { done: 1, total: 2 })
+        );
+        assert_eq!(index.subtask_progress(2), None);
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let finished = |id, day| Task {
+            id,
+            status: Status::Done,
+            changes: vec![Change {
+                from: Status::Doing,
+                to: Status::Done,
+                on: "2021-09-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::days(day),
+                by: None,
+            }],
+            ..Default::default()
+        };
+        let mut index = Index::new("Foo").unwrap();
+        index.tasks = vec![
+            finished(1, 0),
+            finished(2, 10),
+            Task {
+                id: 3,
+                status: Status::Doing,
+                ..Default::default()
+            },
+        ];
+        let ids = |tasks: &[Task]| tasks.iter().map(|t| t.id).collect::<Vec<_>>();
+
+        let day = |d| NaiveDate::from_ymd_opt(2021, 9, d).unwrap();
+        assert_eq!(index.move_to_archive(day(5)), vec![1]);
+        assert_eq!(ids(&index.tasks), vec![2, 3]);
+        assert!(index.get_task(1).is_none());
+        assert_eq!(index.find_task(1).map(|t| t.status), Some(Status::Done));
+        assert!(index.is_archived(1));
+        assert_eq!(ids(&index.all_tasks()), vec![2, 3, 1]);
+        assert_eq!(index.next_id(), 4);
+
+        assert_eq!(index.move_to_archive(day(30)), vec![2]);
+        assert_eq!(ids(&index.archived), vec![1, 2]);
+
+        index.move_from_archive(1).unwrap();
+        assert_eq!(ids(&index.tasks), vec![1, 3]);
+        assert_eq!(ids(&index.archived), vec![2]);
+        assert!(!index.is_archived(1));
+        assert!(index.move_from_archive(3).is_err());
+    }
+
     #[test]
     fn task_sorting_without_priorities() {
         let tasks = vec![
             Task {
                 id: 1,
                 status: Status::Done,
-                changes: vec![],
-                priority: None,
+                ..Default::default()
             },
             Task {
                 id: 2,
                 status: Status::Done,
-                changes: vec![],
-                priority: None,
+                ..Default::default()
             },
         ];
 
         let index = Index {
             meta: Meta {
                 name: "Foo".to_string(),
+                ..Default::default()
             },
             tasks,
+            archived: vec![],
+            trashed: vec![],
         };
         let retrieved_tasks = index.sorted_tasks_with_status(Status::Done).unwrap();
         let ids: Vec<_> = retrieved_tasks.iter().map(|t| t.id).collect();
@@ -426,22 +1454,24 @@ tasks:
             Task {
                 id: 1,
                 status: Status::Done,
-                changes: vec![],
                 priority: Some(100),
+                ..Default::default()
             },
             Task {
                 id: 2,
                 status: Status::Done,
-                changes: vec![],
-                priority: None,
+                ..Default::default()
             },
         ];
 
         let index = Index {
             meta: Meta {
                 name: "Foo".to_string(),
+                ..Default::default()
             },
             tasks,
+            archived: vec![],
+            trashed: vec![],
         };
         let retrieved_tasks = index.sorted_tasks_with_status(Status::Done).unwrap();
         let ids: Vec<_> = retrieved_tasks.iter().map(|t| t.id).collect();
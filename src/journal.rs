@@ -0,0 +1,362 @@
+use crate::git;
+use crate::storage::{self, Storage};
+use chrono::{DateTime, Utc};
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const MAX_OPERATIONS: usize = 100;
+
+const JOURNAL_REF: &str = "refs/pm/journal";
+
+static RECORDING: Mutex<Option<Operation>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub source: bool,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub command: String,
+    pub on: DateTime<Utc>,
+    pub changes: Vec<FileChange>,
+}
+
+impl Operation {
+    pub fn new(command: String) -> Operation {
+        Operation {
+            command,
+            on: Utc::now(),
+            changes: Vec::new(),
+        }
+    }
+
+    fn before(&mut self, path: &str, source: bool, blob: Option<String>) {
+        // only the first change to a file matters for undoing it
+        if !self
+            .changes
+            .iter()
+            .any(|c| c.path == path && c.source == source)
+        {
+            self.changes.push(FileChange {
+                path: path.to_string(),
+                source,
+                after: blob.clone(),
+                before: blob,
+            });
+        }
+    }
+
+    fn after(&mut self, path: &str, source: bool, blob: Option<String>) {
+        if let Some(change) = self
+            .changes
+            .iter_mut()
+            .find(|c| c.path == path && c.source == source)
+        {
+            change.after = blob;
+        }
+    }
+
+    fn prune(&mut self) {
+        self.changes.retain(|c| c.before != c.after);
+    }
+
+    fn blobs(&self) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .flat_map(|c| c.before.iter().chain(c.after.iter()))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Journal {
+    #[serde(default)]
+    done: Vec<Operation>,
+    #[serde(default)]
+    undone: Vec<Operation>,
+}
+
+fn journal_path() -> Result<PathBuf> {
+    Ok(git::git_dir()?.join("pm-journal.yml"))
+}
+
+fn load() -> Result<Journal> {
+    let path = journal_path()?;
+    if !path.is_file() {
+        return Ok(Journal::default());
+    }
+    let contents = std::fs::read_to_string(&path).wrap_err("reading journal")?;
+    serde_yaml::from_str(&contents).wrap_err("parsing journal")
+}
+
+fn save(journal: &Journal) -> Result<()> {
+    let body = serde_yaml::to_string(journal).wrap_err("serializing journal")?;
+    std::fs::write(journal_path()?, body).wrap_err("writing journal")?;
+    keep_blobs(journal).wrap_err("updating journal ref")
+}
+
+fn keep_blobs(journal: &Journal) -> Result<()> {
+    // refs/pm/journal stops git gc from removing the blobs the journal refers to
+    let blobs: BTreeSet<&String> = journal
+        .done
+        .iter()
+        .chain(&journal.undone)
+        .flat_map(Operation::blobs)
+        .collect();
+    let entries: String = blobs
+        .iter()
+        .map(|id| format!("100644 blob {}\t{}\n", id, id))
+        .collect();
+    let tree = git::run_with(&["mktree"], &[], Some(entries.as_bytes()))?;
+    let tree = String::from_utf8_lossy(&tree).trim().to_string();
+    // the commit only exists to hold the tree, so it does not need the user's identity
+    let ident = [
+        ("GIT_AUTHOR_NAME", "git pm"),
+        ("GIT_AUTHOR_EMAIL", "git-pm@localhost"),
+        ("GIT_COMMITTER_NAME", "git pm"),
+        ("GIT_COMMITTER_EMAIL", "git-pm@localhost"),
+    ];
+    let commit = git::run_with(
+        &["commit-tree", &tree, "-m", "git pm journal"],
+        &ident,
+        None,
+    )?;
+    let commit = String::from_utf8_lossy(&commit).trim().to_string();
+    git::run(&["update-ref", JOURNAL_REF, &commit])?;
+    Ok(())
+}
+
+fn blob(contents: Option<&[u8]>) -> Result<Option<String>> {
+    contents.map(git::write_blob).transpose()
+}
+
+pub fn begin(command: String) {
+    *RECORDING.lock().unwrap() = Some(Operation::new(command));
+}
+
+pub fn before_change(storage: &Storage, path: &str) -> Result<()> {
+    if RECORDING.lock().unwrap().is_none() {
+        return Ok(());
+    }
+    let contents = storage.read(path)?;
+    let blob = blob(contents.as_deref()).wrap_err("recording file in journal")?;
+    if let Some(op) = RECORDING.lock().unwrap().as_mut() {
+        op.before(path, false, blob);
+    }
+    Ok(())
+}
+
+pub fn after_change(path: &str, contents: Option<&[u8]>) -> Result<()> {
+    let mut recording = RECORDING.lock().unwrap();
+    if let Some(op) = recording.as_mut() {
+        op.after(
+            path,
+            false,
+            blob(contents).wrap_err("recording file in journal")?,
+        );
+    }
+    Ok(())
+}
+
+pub fn before_source_change(path: &str) -> Result<()> {
+    if RECORDING.lock().unwrap().is_none() {
+        return Ok(());
+    }
+    let contents = read_source(path)?;
+    let blob = blob(contents.as_deref()).wrap_err("recording file in journal")?;
+    if let Some(op) = RECORDING.lock().unwrap().as_mut() {
+        op.before(path, true, blob);
+    }
+    Ok(())
+}
+
+pub fn after_source_change(path: &str, contents: &[u8]) -> Result<()> {
+    let mut recording = RECORDING.lock().unwrap();
+    if let Some(op) = recording.as_mut() {
+        op.after(
+            path,
+            true,
+            blob(Some(contents)).wrap_err("recording file in journal")?,
+        );
+    }
+    Ok(())
+}
+
+fn read_source(path: &str) -> Result<Option<Vec<u8>>> {
+    let path = storage::find_project_root()?.join(path);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let contents = std::fs::read(&path).wrap_err_with(|| format!("reading {:?}", path))?;
+    Ok(Some(contents))
+}
+
+fn read(storage: &Storage, change: &FileChange) -> Result<Option<Vec<u8>>> {
+    if change.source {
+        read_source(&change.path)
+    } else {
+        storage.read(&change.path)
+    }
+}
+
+fn write(storage: &Storage, change: &FileChange, contents: Option<&[u8]>) -> Result<()> {
+    if !change.source {
+        return match contents {
+            Some(contents) => storage.write(&change.path, contents),
+            None => storage.remove(&change.path),
+        };
+    }
+    let path = storage::find_project_root()?.join(&change.path);
+    match contents {
+        Some(contents) => {
+            std::fs::write(&path, contents).wrap_err_with(|| format!("writing {:?}", path))
+        }
+        None => std::fs::remove_file(&path).wrap_err_with(|| format!("removing {:?}", path)),
+    }
+}
+
+pub fn finish() -> Result<()> {
+    let op = RECORDING.lock().unwrap().take();
+    let mut op = match op {
+        Some(op) => op,
+        None => return Ok(()),
+    };
+    op.prune();
+    if op.changes.is_empty() {
+        return Ok(());
+    }
+    let mut journal = load()?;
+    journal.done.push(op);
+    let excess = journal.done.len().saturating_sub(MAX_OPERATIONS);
+    journal.done.drain(..excess);
+    journal.undone.clear();
+    save(&journal)
+}
+
+fn apply(
+    storage: &Storage,
+    op: &Operation,
+    verb: &str,
+    from: impl Fn(&FileChange) -> &Option<String>,
+    to: impl Fn(&FileChange) -> &Option<String>,
+) -> Result<()> {
+    for change in &op.changes {
+        let current = read(storage, change)?;
+        let current = current.as_deref().map(git::write_blob).transpose()?;
+        if &current != from(change) {
+            let path = if change.source {
+                change.path.clone()
+            } else {
+                storage.repo_path(&change.path)
+            };
+            return Err(eyre::eyre!(
+                "{} has changed since `git pm {}`, refusing to {} it",
+                path,
+                op.command,
+                verb
+            ));
+        }
+    }
+    for change in op.changes.iter().rev() {
+        let contents = match to(change) {
+            Some(id) => Some(git::read_blob(id)?.ok_or_else(|| {
+                eyre::eyre!("the contents of {} are no longer available", change.path)
+            })?),
+            None => None,
+        };
+        write(storage, change, contents.as_deref())?;
+    }
+    Ok(())
+}
+
+pub fn undo() -> Result<Option<Operation>> {
+    let mut journal = load()?;
+    let op = match journal.done.pop() {
+        Some(op) => op,
+        None => return Ok(None),
+    };
+    apply(
+        &Storage::current()?,
+        &op,
+        "undo",
+        |c| &c.after,
+        |c| &c.before,
+    )?;
+    // the board must hold the change before the journal says it was made
+    storage::commit("undo")?;
+    journal.undone.push(op.clone());
+    save(&journal)?;
+    Ok(Some(op))
+}
+
+pub fn redo() -> Result<Option<Operation>> {
+    let mut journal = load()?;
+    let op = match journal.undone.pop() {
+        Some(op) => op,
+        None => return Ok(None),
+    };
+    apply(
+        &Storage::current()?,
+        &op,
+        "redo",
+        |c| &c.before,
+        |c| &c.after,
+    )?;
+    storage::commit("redo")?;
+    journal.done.push(op.clone());
+    save(&journal)?;
+    Ok(Some(op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_changes() {
+        let blob = |s: &str| Some(s.to_string());
+        let mut op = Operation::new("delete 3".to_string());
+        op.before("index.yml", false, blob("a"));
+        op.after("index.yml", false, blob("b"));
+        op.before("index.yml", false, blob("b"));
+        op.after("index.yml", false, blob("c"));
+        op.before("tasks/003.md", false, blob("d"));
+        op.after("tasks/003.md", false, None);
+        op.before("comments/003.yml", false, None);
+        op.after("comments/003.yml", false, None);
+        op.before("index.yml", true, blob("e"));
+        op.after("index.yml", true, blob("f"));
+        op.prune();
+
+        assert_eq!(
+            op.changes,
+            vec![
+                FileChange {
+                    path: "index.yml".to_string(),
+                    source: false,
+                    before: blob("a"),
+                    after: blob("c"),
+                },
+                FileChange {
+                    path: "tasks/003.md".to_string(),
+                    source: false,
+                    before: blob("d"),
+                    after: None,
+                },
+                FileChange {
+                    path: "index.yml".to_string(),
+                    source: true,
+                    before: blob("e"),
+                    after: blob("f"),
+                },
+            ]
+        );
+    }
+}
@@ -1,10 +1,34 @@
 use eyre::{Result, WrapErr};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
 use std::process;
 use structopt::StructOpt;
 
+mod archive;
+mod attachment;
+mod changelog;
+mod chart;
+mod comment;
+mod dates;
+mod diff;
 mod error;
+mod estimate;
+mod git;
+mod graph;
 mod highlighter;
+mod history;
 mod index;
+mod journal;
+mod milestone;
+mod sprint;
+mod stale;
+mod standup;
+mod stats;
+mod storage;
+mod throughput;
+mod todo;
+mod trash;
+mod xref;
 
 #[derive(StructOpt)]
 enum Opts {
@@ -13,12 +37,34 @@ enum Opts {
         name: String,
         #[structopt(short, long)]
         force: bool,
+        #[structopt(long, default_value = "worktree")]
+        storage: storage::Storage,
     },
     Add {
+        /// Add the task as a subtask of this task
+        #[structopt(long)]
+        parent: Option<u64>,
         entry: Vec<String>,
     },
     Show {
         task_id: Option<u64>,
+        #[structopt(long)]
+        stale: Vec<String>,
+        /// Only show tasks assigned to the current git user
+        #[structopt(long)]
+        mine: bool,
+        /// Only show unfinished tasks past their due date
+        #[structopt(long)]
+        overdue: bool,
+        /// Only show tasks due before this day
+        #[structopt(long)]
+        due_before: Option<String>,
+        /// Only show tasks in this milestone
+        #[structopt(long)]
+        milestone: Option<String>,
+        /// Include archived tasks
+        #[structopt(long)]
+        archived: bool,
     },
     Inc {
         task_id: u64,
@@ -29,19 +75,296 @@ enum Opts {
     Move {
         task_id: u64,
         status: index::Status,
+        /// Start the task even if it is blocked by unfinished tasks
+        #[structopt(long)]
+        force: bool,
     },
+    /// Move a task to the trash
     Delete {
         task_id: u64,
+        /// Delete the task for good instead, along with its comments and attachments
+        #[structopt(long)]
+        hard: bool,
+    },
+    /// Move a task out of the trash and back onto the board
+    Restore {
+        task_id: u64,
     },
     Edit {
         task_id: u64,
     },
+    Log {
+        task_id: u64,
+    },
+    Due {
+        task_id: u64,
+        /// A date, `today`, `tomorrow`, a weekday or an offset such as `+3d`. Clears
+        /// the due date if not given.
+        day: Option<String>,
+    },
+    Schedule {
+        task_id: u64,
+        /// A date, `today`, `tomorrow`, a weekday or an offset such as `+3d`. Clears
+        /// the scheduled date if not given.
+        day: Option<String>,
+    },
+    /// Record that a task cannot start until another is finished
+    Depend {
+        task_id: u64,
+        #[structopt(name = "on", possible_values = &["on"])]
+        _on: String,
+        blocker_id: u64,
+        /// Remove the dependency instead
+        #[structopt(long)]
+        remove: bool,
+    },
+    /// Print the dependency graph
+    Graph {
+        /// Output Graphviz DOT instead of text
+        #[structopt(long)]
+        dot: bool,
+    },
+    /// Copy a file into a task's attachments
+    Attach {
+        task_id: u64,
+        file: PathBuf,
+    },
+    /// Add a comment to a task's discussion thread
+    Comment {
+        task_id: u64,
+        text: Vec<String>,
+    },
+    Estimate {
+        task_id: u64,
+        /// Story points such as `3` or `3pt`, or hours such as `4h`. Clears the
+        /// estimate if not given.
+        estimate: Option<estimate::Estimate>,
+    },
+    Timer(TimerCommand),
+    Milestone(MilestoneCommand),
+    Sprint(SprintCommand),
+    Trash(TrashCommand),
+    Assign {
+        task_id: u64,
+        /// The name to assign, or `me` for the current git user
+        who: String,
+    },
+    Unassign {
+        task_id: u64,
+        /// The name to unassign, or every assignee if not given
+        who: Option<String>,
+    },
     Start {
         task_id: u64,
+        /// Start the task even if it is blocked by unfinished tasks
+        #[structopt(long)]
+        force: bool,
     },
     Finish {
         task_id: u64,
     },
+    Limit {
+        status: index::Status,
+        limit: Option<usize>,
+    },
+    Diff {
+        range: Option<String>,
+        #[structopt(long)]
+        textconv: Option<PathBuf>,
+    },
+    Changelog {
+        #[structopt(long)]
+        since: String,
+        #[structopt(long)]
+        append: bool,
+    },
+    Stats {
+        #[structopt(long)]
+        since: Option<String>,
+        #[structopt(long)]
+        until: Option<String>,
+        #[structopt(long)]
+        by: Option<String>,
+        #[structopt(long)]
+        json: bool,
+    },
+    Chart {
+        kind: chart::ChartKind,
+        #[structopt(long)]
+        since: Option<String>,
+        #[structopt(long)]
+        svg: Option<PathBuf>,
+    },
+    Throughput {
+        #[structopt(long, default_value = "week")]
+        per: throughput::Period,
+        #[structopt(long, default_value = "4")]
+        window: usize,
+    },
+    Stale {
+        /// Defaults to `7d` for `Todo` and `Doing`
+        #[structopt(long)]
+        older_than: Vec<String>,
+    },
+    Standup {
+        #[structopt(long, default_value = "yesterday")]
+        since: String,
+        #[structopt(long)]
+        author: Option<String>,
+    },
+    /// Check the board for references to tasks which no longer exist
+    Fsck,
+    /// Move tasks finished before a day out of the index and into the archive
+    Archive {
+        #[structopt(long)]
+        done_before: String,
+    },
+    /// Move archived tasks back onto the board
+    Unarchive {
+        task_ids: Vec<u64>,
+    },
+    /// Reverse the most recent change to the board
+    Undo,
+    /// Repeat the most recently undone change
+    Redo,
+    /// Find TODO comments in tracked files and link them to tasks
+    Scan {
+        /// Create a task for every untracked TODO without asking
+        #[structopt(long)]
+        create: bool,
+    },
+}
+
+#[derive(StructOpt)]
+enum TimerCommand {
+    /// Start logging work on a task
+    Start { task_id: u64 },
+    /// Stop logging work on a task
+    Stop { task_id: u64 },
+}
+
+#[derive(StructOpt)]
+enum MilestoneCommand {
+    /// Declare a new milestone
+    Add {
+        name: String,
+        /// The day the milestone should be reached by
+        #[structopt(long)]
+        target: Option<String>,
+        #[structopt(long, default_value = "")]
+        description: String,
+    },
+    /// List the milestones and their progress
+    List,
+    /// Show the progress of a milestone and when it is projected to be reached
+    Show { name: String },
+    /// Put a task into a milestone, or take it out if no milestone is given
+    Set { task_id: u64, name: Option<String> },
+    /// Remove a milestone, taking its tasks out of it
+    Remove { name: String },
+}
+
+#[derive(StructOpt)]
+enum TrashCommand {
+    /// List the deleted tasks
+    List,
+    /// Delete tasks which have been in the trash for a while for good
+    Purge {
+        #[structopt(long, default_value = "30d")]
+        older_than: String,
+    },
+}
+
+#[derive(StructOpt)]
+enum SprintCommand {
+    /// Open a new sprint
+    Start {
+        /// Defaults to `Sprint <n>`
+        #[structopt(long)]
+        name: Option<String>,
+        /// The first day of the sprint, today if not given
+        #[structopt(long)]
+        start: Option<String>,
+        #[structopt(long, default_value = "2w")]
+        length: String,
+    },
+    /// Pull tasks into the current sprint, or every `Todo` task if none are given
+    Plan { task_ids: Vec<u64> },
+    /// Close the current sprint, rolling unfinished tasks over into the next one
+    Close,
+    /// List the sprints
+    List,
+    /// Show the tasks and burndown of a sprint, the current one if not given
+    Show {
+        name: Option<String>,
+        #[structopt(long)]
+        svg: Option<PathBuf>,
+    },
+}
+
+/// Options controlling how the board is shown
+#[derive(Default)]
+struct ShowOptions {
+    stale: stale::Thresholds,
+    /// Only show tasks assigned to one of these names
+    assigned_to: Option<Vec<String>>,
+    /// Only show tasks due before this day
+    due_before: Option<chrono::NaiveDate>,
+    overdue: bool,
+    /// Only show tasks in this milestone
+    milestone: Option<String>,
+    /// Include archived tasks
+    archived: bool,
+}
+
+impl ShowOptions {
+    /// Whether a task passes the filters
+    fn includes(
+        &self,
+        task: &index::Task,
+        detail: &index::TaskDetail,
+        today: chrono::NaiveDate,
+    ) -> bool {
+        if let Some(names) = &self.assigned_to {
+            if !detail.is_assigned_to(names) {
+                return false;
+            }
+        }
+        if let Some(before) = self.due_before {
+            if !task.due.map(|d| d < before).unwrap_or(false) {
+                return false;
+            }
+        }
+        if let Some(milestone) = &self.milestone {
+            if task.milestone.as_ref() != Some(milestone) {
+                return false;
+            }
+        }
+        !self.overdue || task.is_overdue(today)
+    }
+}
+
+/// Describe the due and scheduled dates of an unfinished task, highlighting overdue
+/// tasks when writing to a terminal
+fn format_dates(task: &index::Task, today: chrono::NaiveDate) -> String {
+    let mut out = String::new();
+    if task.status == index::Status::Done {
+        return out;
+    }
+    if let Some(scheduled) = task.scheduled {
+        out.push_str(&format!(" scheduled {}", scheduled));
+    }
+    if let Some(due) = task.due {
+        out.push_str(&format!(" due {}", due));
+    }
+    if task.is_overdue(today) {
+        if std::io::stdout().is_terminal() {
+            out.push_str(" \x1b[1;31m(overdue)\x1b[0m");
+        } else {
+            out.push_str(" (overdue)");
+        }
+    }
+    out
 }
 
 struct Manager<'a> {
@@ -49,36 +372,66 @@ struct Manager<'a> {
 }
 
 impl<'a> Manager<'a> {
-    fn init(&self, name: String, force: bool) -> Result<()> {
+    fn init(&self, name: String, force: bool, storage: storage::Storage) -> Result<()> {
+        let other = match storage {
+            storage::Storage::WorkTree => storage::Storage::Ref,
+            storage::Storage::Ref => storage::Storage::WorkTree,
+        };
+        if other.has_board()? {
+            return Err(eyre::eyre!(
+                "a board already exists in {} storage, refusing to switch to {} storage",
+                other,
+                storage
+            ));
+        }
+        storage::Storage::select(storage);
         let index = index::Index::new(name).wrap_err("loading configuration")?;
         match index.save(force) {
-            Ok(_) => {}
-            Err(e) => {
-                if e.is::<crate::error::PmError>() {
-                    match e.downcast::<crate::error::PmError>() {
-                        Ok(crate::error::PmError::IndexExists) => {
-                            eprintln!("index already exists, not overwriting");
-                            std::process::exit(1);
-                        }
-                        Err(e) => return Err(e.into()),
-                    }
+            Ok(_) => storage.record()?,
+            Err(e) => match e.downcast::<crate::error::PmError>() {
+                Ok(crate::error::PmError::IndexExists) => {
+                    eprintln!("index already exists, not overwriting");
+                    std::process::exit(1);
                 }
-            }
+                Ok(e) => return Err(e.into()),
+                Err(e) => return Err(e),
+            },
         }
         Ok(())
     }
 
-    fn add(&mut self, entry: Vec<String>) -> Result<()> {
+    fn add(&mut self, entry: Vec<String>, parent: Option<u64>) -> Result<()> {
         let mut index = index::Index::load().wrap_err("loading index")?;
-        index.create_task(&entry).wrap_err("creating task")?;
+        index
+            .create_task(&entry, parent)
+            .wrap_err("creating task")?;
         self.show(None).wrap_err("showing")?;
         Ok(())
     }
 
     fn show(&mut self, task_id: Option<u64>) -> Result<()> {
-        let index = index::Index::load().wrap_err("loading index")?;
+        self.show_with(task_id, &ShowOptions::default())
+    }
+
+    fn show_with(&mut self, task_id: Option<u64>, options: &ShowOptions) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        if options.archived {
+            // showing never saves the index, so the archived tasks can be put back on
+            // the board for this view
+            index.tasks.extend(index.archived.iter().cloned());
+        }
         if let Some(id) = task_id {
-            let task = index.get_task(id).expect("could not find task in index");
+            let task = index.find_task(id).ok_or_else(|| {
+                if index.is_trashed(id) {
+                    eyre::eyre!(
+                        "task {} is in the trash, `git pm restore {}` to get it back",
+                        id,
+                        id
+                    )
+                } else {
+                    eyre::eyre!("could not find task {}", id)
+                }
+            })?;
             let detail = task.detail().wrap_err("fetching task detail")?;
 
             let summary = detail.summary.trim();
@@ -90,8 +443,96 @@ impl<'a> Manager<'a> {
             println!();
             // TODO: nice formatting and colours
             // println!("{}", detail.description.trim());
+            let mut fields = Vec::new();
+            if index.is_archived(task.id) {
+                fields.push("Archived".to_string());
+            }
+            if !detail.assignees.is_empty() {
+                fields.push(format!("Assigned to {}", detail.assignees.join(", ")));
+            }
+            let dates = format_dates(task, chrono::Utc::now().naive_utc().date());
+            if !dates.is_empty() {
+                fields.push(dates.trim_start().to_string());
+            }
+            fields.extend(format_effort(task, &detail, chrono::Utc::now()));
+            if !task.blocked_by.is_empty() {
+                fields.push(format!(
+                    "Blocked by {}",
+                    format_ids(&index, &task.blocked_by)
+                ));
+            }
+            if let Some(milestone) = &task.milestone {
+                fields.push(format!("Milestone {}", milestone));
+            }
+            if let Some(parent) = task.parent {
+                fields.push(format!("Part of {}", format_ids(&index, &[parent])));
+            }
+            if let Some(progress) = index.subtask_progress(task.id) {
+                let children: Vec<_> = index.children(task.id).iter().map(|t| t.id).collect();
+                fields.push(format!(
+                    "Subtasks {} done: {}",
+                    progress,
+                    format_ids(&index, &children)
+                ));
+            }
+            if let Some(progress) = detail.checklist() {
+                fields.push(format!("Checklist {} done", progress));
+            }
+            let blocks = graph::blocks(&index.tasks, task.id);
+            if !blocks.is_empty() {
+                fields.push(format!("Blocks {}", format_ids(&index, &blocks)));
+            }
+            if !fields.is_empty() {
+                println!("{}", fields.join("\n"));
+                println!();
+            }
             self.highlighter.print(detail.description.trim());
             println!();
+
+            let references = xref::mentions(task.id, &detail);
+            if !references.is_empty() {
+                println!();
+                print_references(&index, "References", &references)?;
+            }
+            let referenced_by = xref::referenced_by(&index.all_tasks(), task.id);
+            if !referenced_by.is_empty() {
+                println!();
+                print_references(&index, "Referenced by", &referenced_by)?;
+            }
+
+            let todos = todo::linked_to(task.id).wrap_err("finding TODO comments")?;
+            if !todos.is_empty() {
+                println!();
+                println!("Code");
+                println!("----");
+                for todo in &todos {
+                    println!("{}", todo);
+                }
+            }
+
+            if !detail.attachments.is_empty() {
+                println!();
+                println!("Attachments");
+                println!("-----------");
+                let storage = storage::Storage::current()?;
+                for name in &detail.attachments {
+                    let size = match attachment::size(task.id, name)? {
+                        Some(size) => attachment::format_size(size),
+                        None => "missing".to_string(),
+                    };
+                    let path = storage.repo_path(&attachment::attachment_file(task.id, name));
+                    println!("{}  ({})  {}", name, size, path);
+                }
+            }
+
+            let comments = comment::load(task.id).wrap_err("loading comments")?;
+            if !comments.is_empty() {
+                println!();
+                print_comments(&comments);
+            }
+
+            println!();
+            print_history(task);
         } else {
             // let mut store: HashMap<index::Status, Vec<&index::Task>> = HashMap::new();
 
@@ -100,6 +541,8 @@ impl<'a> Manager<'a> {
             //     e.push(task);
             // }
 
+            let now = chrono::Utc::now();
+            let today = now.naive_utc().date();
             let to_print_statuses = &[
                 index::Status::Todo,
                 index::Status::Doing,
@@ -108,60 +551,658 @@ impl<'a> Manager<'a> {
 
             for status in to_print_statuses {
                 println!("----------");
-                println!("{}", status);
-
-                match index.sorted_tasks_with_status(*status) {
-                    None => println!("... no tasks found"),
-                    Some(ts) => {
-                        for task in ts {
-                            let detail = task.detail().wrap_err_with(|| {
-                                format!("reading task detail for task {}", task.id)
-                            })?;
-                            if !detail.tags.is_empty() {
-                                let tags_entry = {
-                                    let tags =
-                                        detail.tags.iter().map(|t| t.as_str()).collect::<Vec<_>>();
-                                    tags.join(" ")
-                                };
-                                println!("{:03}: {}\t\t:{}:", task.id, detail.summary, tags_entry);
-                            } else {
-                                println!("{:03}: {}", task.id, detail.summary);
-                            }
-                        }
+                match index.meta.wip_limits.get(status) {
+                    Some(limit) => println!(
+                        "{} ({}/{})",
+                        status,
+                        index.count_with_status(*status),
+                        limit
+                    ),
+                    None => println!("{}", status),
+                }
+
+                let mut cards = Vec::new();
+                for task in index.sorted_tasks_with_status(*status).unwrap_or_default() {
+                    let detail = task
+                        .detail()
+                        .wrap_err_with(|| format!("reading task detail for task {}", task.id))?;
+                    if !options.includes(&task, &detail, today) {
+                        continue;
+                    }
+                    let mut line = format!("{:03}: {}", task.id, detail.summary);
+                    if !detail.tags.is_empty() {
+                        let tags_entry = {
+                            let tags = detail.tags.iter().map(|t| t.as_str()).collect::<Vec<_>>();
+                            tags.join(" ")
+                        };
+                        line.push_str(&format!("\t\t:{}:", tags_entry));
+                    }
+                    for assignee in &detail.assignees {
+                        line.push_str(&format!(" @{}", assignee));
+                    }
+                    if let Some(progress) = index.subtask_progress(task.id) {
+                        line.push_str(&format!(" [{} subtasks]", progress));
+                    }
+                    if let Some(progress) = detail.checklist() {
+                        line.push_str(&format!(" [{}]", progress));
+                    }
+                    line.push_str(&format_dates(&task, today));
+                    if task.status == index::Status::Todo
+                        && !index.unfinished_blockers(task.id).is_empty()
+                    {
+                        line.push_str(" (blocked)");
+                    }
+                    if let Some(age) = options.stale.stale_age(&task, now) {
+                        line.push_str(&format!(" (stale for {})", dates::format_duration(age)));
+                    }
+                    if index.is_archived(task.id) {
+                        line.push_str(" (archived)");
+                    }
+                    cards.push((task, line));
+                }
+                if cards.is_empty() {
+                    println!("... no tasks found");
+                }
+                print_cards(&cards, None, 0);
+                println!();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn log(&self, task_id: u64) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let task = index
+            .find_task(task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        let detail = task.detail().wrap_err("fetching task detail")?;
+        println!("{:03}: {}", task.id, detail.summary.trim());
+        println!();
+        print_history(task);
+
+        let storage = storage::Storage::current()?;
+        let rev = storage.default_rev();
+        let edits = if git::resolve(rev)?.is_some() {
+            // an archived task's edits were made before it moved into the archive
+            let paths = [
+                storage.repo_path(&index::detail_file(task_id)),
+                storage.repo_path(&archive::detail_file(task_id)),
+            ];
+            git::log(rev, &paths, None).wrap_err("listing task edits")?
+        } else {
+            Vec::new()
+        };
+
+        println!();
+        println!("Edits");
+        println!("-----");
+        if edits.is_empty() {
+            println!("... no commits found");
+        }
+        for commit in edits {
+            println!(
+                "{:.8} {}  {}: {}",
+                commit.id,
+                commit.date.format("%Y-%m-%d %H:%M"),
+                commit.author_name,
+                commit.subject
+            );
+        }
+        Ok(())
+    }
+
+    fn set_date(&mut self, task_id: u64, kind: index::DateKind, day: Option<String>) -> Result<()> {
+        let today = chrono::Utc::now().naive_utc().date();
+        let date = day
+            .map(|day| {
+                dates::parse_day(&day, today)
+                    .ok_or_else(|| eyre::eyre!("invalid {} date {}", kind, day))
+            })
+            .transpose()?;
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        index
+            .update_task_date(task_id, kind, date)
+            .wrap_err("updating task")?;
+        self.show(None).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn depend(&mut self, task_id: u64, blocker_id: u64, remove: bool) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        if remove {
+            index
+                .remove_dependency(task_id, blocker_id)
+                .wrap_err("removing dependency")?;
+        } else {
+            index
+                .add_dependency(task_id, blocker_id)
+                .wrap_err("adding dependency")?;
+        }
+        self.show(None).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn graph(&self, dot: bool) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        if let Some(cycle) = graph::find_cycle(&index.tasks) {
+            let cycle: Vec<_> = cycle.iter().map(|id| id.to_string()).collect();
+            eprintln!("warning: dependency cycle {}", cycle.join(" -> "));
+        }
+        let summaries = index
+            .tasks
+            .iter()
+            .map(|t| Ok((t.id, t.detail()?.summary)))
+            .collect::<Result<_>>()
+            .wrap_err("reading task details")?;
+        if dot {
+            print!("{}", graph::render_dot(&index.tasks, &summaries));
+        } else {
+            print!("{}", graph::render_text(&index.tasks, &summaries));
+        }
+        Ok(())
+    }
+
+    fn milestone(&mut self, command: MilestoneCommand) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        let today = chrono::Utc::now().naive_utc().date();
+        match command {
+            MilestoneCommand::Add {
+                name,
+                target,
+                description,
+            } => {
+                let target = target
+                    .map(|day| {
+                        dates::parse_day(&day, today)
+                            .ok_or_else(|| eyre::eyre!("invalid target date {}", day))
+                    })
+                    .transpose()?;
+                index
+                    .add_milestone(milestone::Milestone {
+                        name,
+                        target,
+                        description,
+                    })
+                    .wrap_err("adding milestone")?;
+            }
+            MilestoneCommand::List => {
+                if index.meta.milestones.is_empty() {
+                    println!("... no milestones found");
+                }
+                let tasks = index.all_tasks();
+                for m in &index.meta.milestones {
+                    let report = milestone::Report::new(m, &tasks, today);
+                    let target = m.target.map(|t| format!("  due {}", t)).unwrap_or_default();
+                    println!("{}  {} done{}", m.name, report.progress, target);
+                }
+            }
+            MilestoneCommand::Show { name } => {
+                let m = index
+                    .milestone(&name)
+                    .ok_or_else(|| eyre::eyre!("could not find milestone {}", name))?;
+                let tasks = index.all_tasks();
+                let report = milestone::Report::new(m, &tasks, today);
+                print!("{}", report);
+                if !report.remaining.is_empty() {
+                    println!();
+                    println!("Remaining");
+                    for task in &report.remaining {
+                        let detail = task.detail().wrap_err_with(|| {
+                            format!("reading task detail for task {}", task.id)
+                        })?;
+                        println!("{:03}: {} [{}]", task.id, detail.summary, task.status);
                     }
                 }
+            }
+            MilestoneCommand::Set { task_id, name } => index
+                .update_task_milestone(task_id, name)
+                .wrap_err("updating task")?,
+            MilestoneCommand::Remove { name } => index
+                .remove_milestone(&name)
+                .wrap_err("removing milestone")?,
+        }
+        Ok(())
+    }
+
+    fn sprint(&mut self, command: SprintCommand) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        let today = chrono::Utc::now().naive_utc().date();
+        match command {
+            SprintCommand::Start {
+                name,
+                start,
+                length,
+            } => {
+                let name = name.unwrap_or_else(|| sprint::next_name(&index.meta.sprints));
+                let start = match start {
+                    Some(day) => dates::parse_day(&day, today)
+                        .ok_or_else(|| eyre::eyre!("invalid start date {}", day))?,
+                    None => today,
+                };
+                let length = dates::parse_duration(&length).wrap_err("parsing sprint length")?;
+                if length.num_days() < 1 {
+                    return Err(eyre::eyre!("sprints must last at least a day"));
+                }
+                let sprint = sprint::Sprint::new(name, start, length);
+                let summary = format!(
+                    "{} runs from {} to {}",
+                    sprint.name, sprint.start, sprint.end
+                );
+                index.add_sprint(sprint).wrap_err("adding sprint")?;
+                println!("{}", summary);
+            }
+            SprintCommand::Plan { task_ids } => {
+                let added = index.plan_sprint(&task_ids).wrap_err("planning sprint")?;
+                println!("added {} tasks to the sprint", added.len());
+            }
+            SprintCommand::Close => {
+                let next = index.close_sprint(today).wrap_err("closing sprint")?;
+                println!(
+                    "rolled {} unfinished tasks over into {}, which runs from {} to {}",
+                    next.tasks.len(),
+                    next.name,
+                    next.start,
+                    next.end
+                );
+            }
+            SprintCommand::List => {
+                if index.meta.sprints.is_empty() {
+                    println!("... no sprints found");
+                }
+                for s in &index.meta.sprints {
+                    let done = s
+                        .tasks
+                        .iter()
+                        .filter(|id| {
+                            index.find_task(**id).map(|t| t.status) == Some(index::Status::Done)
+                        })
+                        .count();
+                    println!(
+                        "{}  {} to {}  {}/{} done{}",
+                        s.name,
+                        s.start,
+                        s.end,
+                        done,
+                        s.tasks.len(),
+                        if s.closed { "  (closed)" } else { "" }
+                    );
+                }
+            }
+            SprintCommand::Show { name, svg } => {
+                let sprint = match &name {
+                    Some(name) => index.sprint(name),
+                    None => index.current_sprint(),
+                }
+                .ok_or_else(|| match &name {
+                    Some(name) => eyre::eyre!("could not find sprint {}", name),
+                    None => eyre::eyre!("no sprint is open"),
+                })?;
+                println!("{}: {} to {}", sprint.name, sprint.start, sprint.end);
                 println!();
+                for id in &sprint.tasks {
+                    let task = match index.find_task(*id) {
+                        Some(task) => task,
+                        None => continue,
+                    };
+                    let detail = task
+                        .detail()
+                        .wrap_err_with(|| format!("reading task detail for task {}", task.id))?;
+                    println!("{:03}: {} [{}]", task.id, detail.summary, task.status);
+                }
+                println!();
+
+                let days = sprint.burndown(&index.all_tasks(), today);
+                match svg {
+                    Some(path) => {
+                        let chart = chart::render_svg(chart::ChartKind::Burndown, &days);
+                        std::fs::write(&path, chart)
+                            .wrap_err_with(|| format!("writing {:?}", &path))?;
+                        println!("wrote burndown chart to {:?}", path);
+                    }
+                    None => print!("{}", chart::render_ascii(chart::ChartKind::Burndown, &days)),
+                }
             }
         }
+        Ok(())
+    }
 
+    fn archive(&mut self, done_before: String) -> Result<()> {
+        let today = chrono::Utc::now().naive_utc().date();
+        let done_before = dates::parse_day(&done_before, today)
+            .ok_or_else(|| eyre::eyre!("invalid date {}", done_before))?;
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        let archived = index.archive(done_before)?;
+        if archived.is_empty() {
+            println!("no tasks finished before {}", done_before);
+        } else {
+            println!(
+                "archived {} tasks: {}",
+                archived.len(),
+                format_ids(&index, &archived)
+            );
+        }
         Ok(())
     }
 
-    fn move_task(&mut self, task_id: u64, status: index::Status) -> Result<()> {
+    fn unarchive(&mut self, task_ids: Vec<u64>) -> Result<()> {
         let mut index = index::Index::load().wrap_err("loading index")?;
+        for id in task_ids {
+            index.unarchive(id)?;
+        }
+        self.show(None).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<()> {
+        match journal::undo()? {
+            Some(op) => {
+                println!("undid `git pm {}`", op.command);
+                if storage::Storage::current()?.exists(index::INDEX_FILE)? {
+                    self.show(None).wrap_err("showing")?;
+                }
+            }
+            None => println!("nothing to undo"),
+        }
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<()> {
+        match journal::redo()? {
+            Some(op) => {
+                println!("redid `git pm {}`", op.command);
+                if storage::Storage::current()?.exists(index::INDEX_FILE)? {
+                    self.show(None).wrap_err("showing")?;
+                }
+            }
+            None => println!("nothing to redo"),
+        }
+        Ok(())
+    }
+
+    fn fsck(&self) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let problems = xref::fsck(&index)?;
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        if !problems.is_empty() {
+            return Err(eyre::eyre!("found {} problems", problems.len()));
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self, create: bool) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        let todos = todo::scan()?;
+        let (linked, untracked): (Vec<_>, Vec<_>) =
+            todos.into_iter().partition(|t| t.task.is_some());
+
+        let mut problems = Vec::new();
+        if !linked.is_empty() {
+            println!("Linked");
+            println!("------");
+            for todo in &linked {
+                // unwrap is safe because linked TODOs have a task
+                let id = todo.task.unwrap();
+                match index.find_task(id) {
+                    Some(task) => {
+                        println!("{}  #{} ({})", todo, id, task.status);
+                        if task.status == index::Status::Done {
+                            problems.push(format!(
+                                "{}:{} refers to #{}, which is done",
+                                todo.path, todo.line, id
+                            ));
+                        }
+                    }
+                    None => {
+                        println!("{}  #{} (missing)", todo, id);
+                        problems.push(format!(
+                            "{}:{} refers to #{}, which does not exist",
+                            todo.path, todo.line, id
+                        ));
+                    }
+                }
+            }
+            println!();
+        }
+        if !problems.is_empty() {
+            println!("Needs attention");
+            println!("---------------");
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            println!();
+        }
+        if untracked.is_empty() {
+            return Ok(());
+        }
+
+        println!("Untracked");
+        println!("---------");
+        for todo in &untracked {
+            println!("{}", todo);
+        }
+        let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        if !create && !interactive {
+            return Ok(());
+        }
+        println!();
+        for todo in &untracked {
+            if !create && !confirm(&format!("Create a task for {}?", todo))? {
+                continue;
+            }
+            let entry: Vec<String> = todo
+                .summary()
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+            let id = index.create_task(&entry, None).wrap_err("creating task")?;
+            todo::link(todo, id).wrap_err("linking TODO comment")?;
+            println!("Created {:03} and linked {}:{}", id, todo.path, todo.line);
+        }
+        Ok(())
+    }
+
+    fn attach(&mut self, task_id: u64, file: PathBuf) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let mut detail = self.task_detail(task_id)?;
+        let (name, size) = attachment::store(task_id, &file).wrap_err("storing attachment")?;
+        let warn_size = index
+            .meta
+            .attachment_warn_size
+            .unwrap_or(attachment::DEFAULT_WARN_SIZE);
+        if size > warn_size {
+            eprintln!(
+                "warning: {} is {}, above the {} attachment warning size. Large files bloat \
+                 the repository for everyone who clones it.",
+                name,
+                attachment::format_size(size),
+                attachment::format_size(warn_size)
+            );
+        }
+        detail.attachments.push(name);
+        detail.save().wrap_err("saving task detail")?;
+        self.show(Some(task_id)).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn comment(&mut self, task_id: u64, text: Vec<String>) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        if index.get_task(task_id).is_none() {
+            return Err(eyre::eyre!("could not find task {}", task_id));
+        }
+        let text = text.join(" ");
+        if text.trim().is_empty() {
+            return Err(eyre::eyre!("comment is empty"));
+        }
+        let comment = comment::Comment::new(text.trim())?;
+        comment::add(task_id, comment).wrap_err("adding comment")?;
+        self.show(Some(task_id)).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn set_estimate(&mut self, task_id: u64, estimate: Option<estimate::Estimate>) -> Result<()> {
+        let mut detail = self.task_detail(task_id)?;
+        detail.estimate = estimate;
+        detail.save().wrap_err("saving task detail")?;
+        self.show(Some(task_id)).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn timer(&mut self, command: TimerCommand) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        match command {
+            TimerCommand::Start { task_id } => {
+                index.start_timer(task_id).wrap_err("starting timer")?;
+                println!("started timer on task {}", task_id);
+            }
+            TimerCommand::Stop { task_id } => {
+                let duration = index.stop_timer(task_id).wrap_err("stopping timer")?;
+                println!(
+                    "logged {} on task {}",
+                    dates::format_duration(duration),
+                    task_id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, task_id: u64, who: String) -> Result<()> {
+        let who = if who == "me" {
+            git::user_aliases()?
+                .into_iter()
+                .next()
+                .ok_or_else(|| eyre::eyre!("git user.name and user.email are not set"))?
+        } else {
+            who.trim_start_matches('@').to_string()
+        };
+        let mut detail = self.task_detail(task_id)?;
+        if detail.assign(&who) {
+            detail.save().wrap_err("saving task detail")?;
+        } else {
+            eprintln!("task {} is already assigned to {}", task_id, who);
+        }
+        self.show(Some(task_id)).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn unassign(&mut self, task_id: u64, who: Option<String>) -> Result<()> {
+        let mut detail = self.task_detail(task_id)?;
+        match who {
+            Some(who) => {
+                let who = who.trim_start_matches('@');
+                if !detail.unassign(who) {
+                    return Err(eyre::eyre!("task {} is not assigned to {}", task_id, who));
+                }
+            }
+            None => detail.assignees.clear(),
+        }
+        detail.save().wrap_err("saving task detail")?;
+        self.show(Some(task_id)).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn task_detail(&self, task_id: u64) -> Result<index::TaskDetail> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let task = index
+            .get_task(task_id)
+            .ok_or_else(|| eyre::eyre!("could not find task {}", task_id))?;
+        task.detail().wrap_err("fetching task detail")
+    }
+
+    fn move_task(&mut self, task_id: u64, status: index::Status, force: bool) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        if status == index::Status::Doing && !force {
+            index.check_blockers(task_id)?;
+        }
         index.move_task(task_id, status).wrap_err("moving task")?;
         self.show(None).wrap_err("showing")?;
         Ok(())
     }
 
-    fn delete_task(&mut self, task_id: u64) -> Result<()> {
+    fn delete_task(&mut self, task_id: u64, hard: bool) -> Result<()> {
         let mut index = index::Index::load().wrap_err("loading index")?;
-        index
-            .delete_task(task_id)
-            .wrap_err("deleting task from index")?;
+        if hard {
+            index
+                .delete_task(task_id)
+                .wrap_err("deleting task from index")?;
+        } else {
+            index
+                .trash_task(task_id)
+                .wrap_err("moving task to the trash")?;
+        }
         self.show(None).wrap_err("showing")?;
         Ok(())
     }
 
+    fn restore_task(&mut self, task_id: u64) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        index.restore_task(task_id).wrap_err("restoring task")?;
+        self.show(None).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn trash(&mut self, command: TrashCommand) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        let now = chrono::Utc::now();
+        match command {
+            TrashCommand::List => {
+                if index.trashed.is_empty() {
+                    println!("... no tasks in the trash");
+                }
+                for trashed in &index.trashed {
+                    let detail = trashed.task.detail().wrap_err_with(|| {
+                        format!("reading task detail for task {}", trashed.task.id)
+                    })?;
+                    let mut line = format!(
+                        "{:03}: {}  deleted {}",
+                        trashed.task.id,
+                        detail.summary,
+                        dates::format_ago(trashed.deleted_on, now)
+                    );
+                    if let Some(by) = &trashed.deleted_by {
+                        line.push_str(&format!(" by {}", by));
+                    }
+                    println!("{}", line);
+                }
+            }
+            TrashCommand::Purge { older_than } => {
+                let age = dates::parse_duration(&older_than).wrap_err("parsing age")?;
+                let purged = index.purge_trash(age, now)?;
+                if purged.is_empty() {
+                    println!(
+                        "no tasks have been in the trash for longer than {}",
+                        older_than
+                    );
+                } else {
+                    let ids: Vec<_> = purged.iter().map(|id| format!("{:03}", id)).collect();
+                    println!(
+                        "deleted {} tasks for good: {}",
+                        purged.len(),
+                        ids.join(", ")
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn edit_task(&self, task_id: u64) -> Result<()> {
         let index = index::Index::load().wrap_err("loading index")?;
-        let detail_path = index
-            .detail_path(task_id)
+        if index.get_task(task_id).is_none() {
+            return Err(eyre::eyre!("could not find task {}", task_id));
+        }
+        let storage = storage::Storage::current()?;
+        let detail_file = index::detail_file(task_id);
+        let detail_path = storage
+            .checkout(&detail_file)
             .wrap_err("fetching detail path")?;
 
         let editor = std::env::var("EDITOR").unwrap_or("vim".to_string());
         let mut child = process::Command::new(editor)
-            .args(&[detail_path])
+            .arg(&detail_path)
             .spawn()
             .wrap_err("spawning editor")?;
         let status = child.wait().wrap_err("getting command exit status")?;
@@ -171,6 +1212,273 @@ impl<'a> Manager<'a> {
                 status.code().expect("fetching error code")
             ));
         }
+        storage
+            .checkin(&detail_file, &detail_path)
+            .wrap_err("storing edited task detail")?;
+        Ok(())
+    }
+
+    fn set_wip_limit(&mut self, status: index::Status, limit: Option<usize>) -> Result<()> {
+        let mut index = index::Index::load().wrap_err("loading index")?;
+        match limit {
+            Some(limit) => index.meta.wip_limits.insert(status, limit),
+            None => index.meta.wip_limits.remove(&status),
+        };
+        index.save(true).wrap_err("saving")?;
+        self.show(None).wrap_err("showing")?;
+        Ok(())
+    }
+
+    fn diff(&self, range: Option<String>, textconv: Option<PathBuf>) -> Result<()> {
+        if let Some(path) = textconv {
+            let contents =
+                std::fs::read_to_string(&path).wrap_err_with(|| format!("reading {:?}", &path))?;
+            let index = index::Index::parse(&contents).wrap_err("parsing index")?;
+            print!("{}", diff::render(&index));
+            return Ok(());
+        }
+
+        let storage = storage::Storage::current()?;
+        let (from, to) = diff::parse_range(range.as_deref(), storage.default_rev());
+        let old = diff::Snapshot::at(&from).wrap_err("loading old board")?;
+        let new = match to {
+            Some(rev) => diff::Snapshot::at(&rev).wrap_err("loading new board")?,
+            None => diff::Snapshot::current().wrap_err("loading current board")?,
+        };
+
+        let differences = diff::diff(&old, &new);
+        if differences.is_empty() {
+            println!("no changes");
+        }
+        for difference in differences {
+            println!("{}", difference);
+        }
+        Ok(())
+    }
+
+    fn changelog(&self, since: String, append: bool) -> Result<()> {
+        let start = dates::parse_since(&since).wrap_err("parsing start of window")?;
+        let index = index::Index::load().wrap_err("loading index")?;
+        let tasks = index.all_tasks();
+
+        let mut finished: Vec<_> = tasks
+            .iter()
+            .filter(|t| t.status == index::Status::Done)
+            .filter_map(|t| t.finished_on().map(|on| (on, t)))
+            .filter(|(on, _)| *on >= start)
+            .collect();
+        finished.sort_by_key(|(on, t)| (*on, t.id));
+
+        let details = finished
+            .iter()
+            .map(|(_, t)| {
+                t.detail()
+                    .wrap_err_with(|| format!("reading task detail for task {}", t.id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let title = format!("Changes since {}", since);
+        let rendered = changelog::render(&title, &details);
+
+        if append {
+            let path = storage::find_project_root()?.join("CHANGELOG.md");
+            let mut f = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .wrap_err_with(|| format!("opening {:?}", &path))?;
+            if f.metadata()?.len() > 0 {
+                writeln!(&mut f)?;
+            }
+            write!(&mut f, "{}", rendered)?;
+            println!("added {} tasks to {:?}", details.len(), path);
+        } else {
+            print!("{}", rendered);
+        }
+        Ok(())
+    }
+
+    fn stats(&self, window: dates::Window, by: Option<String>, json: bool) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let by = by.as_deref().map(git::resolve_who).transpose()?;
+        let finished_by = |task: &index::Task| match &by {
+            Some(by) => task
+                .finishing_change()
+                .map(|c| c.made_by(by))
+                .unwrap_or(false),
+            None => true,
+        };
+
+        let mut times = Vec::new();
+        for task in index
+            .all_tasks()
+            .iter()
+            .filter(|t| t.status == index::Status::Done)
+            .filter(|t| finished_by(t))
+        {
+            let detail = task
+                .detail()
+                .wrap_err_with(|| format!("reading task detail for task {}", task.id))?;
+            times.extend(stats::TaskTimes::new(task, &detail));
+        }
+
+        let report = stats::Report::new(times, window);
+        if json {
+            let body = serde_json::to_string_pretty(&report).wrap_err("serializing report")?;
+            println!("{}", body);
+        } else {
+            print!("{}", report);
+        }
+        Ok(())
+    }
+
+    fn chart(
+        &self,
+        kind: chart::ChartKind,
+        since: Option<String>,
+        svg: Option<PathBuf>,
+    ) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let tasks = index.all_tasks();
+        let today = chrono::Utc::now().naive_utc().date();
+        let since = match since {
+            Some(since) => dates::parse_since(&since)
+                .wrap_err("parsing start of chart")?
+                .naive_utc()
+                .date(),
+            None => tasks
+                .iter()
+                .filter_map(|t| t.created_on())
+                .min()
+                .map(|on| on.naive_utc().date())
+                .unwrap_or(today),
+        };
+
+        let days = chart::daily_counts(&tasks, since, today);
+        match svg {
+            Some(path) => {
+                std::fs::write(&path, chart::render_svg(kind, &days))
+                    .wrap_err_with(|| format!("writing {:?}", &path))?;
+                println!("wrote {} chart to {:?}", kind, path);
+            }
+            None => print!("{}", chart::render_ascii(kind, &days)),
+        }
+        Ok(())
+    }
+
+    fn throughput(&self, period: throughput::Period, window: usize) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let today = chrono::Utc::now().naive_utc().date();
+        let buckets = throughput::buckets(&index.all_tasks(), period, window, today);
+
+        println!(
+            "{:<12}{:>6}{:>10}",
+            format!("{} of", period),
+            "done",
+            format!("avg({})", window)
+        );
+        for bucket in &buckets {
+            println!(
+                "{:<12}{:>6}{:>10.1}",
+                bucket.start, bucket.done, bucket.average
+            );
+        }
+        println!();
+
+        let remaining = index.count_with_status(index::Status::Todo);
+        println!("Todo backlog: {} tasks", remaining);
+        let rate = throughput::rate(&buckets, period, today);
+        match throughput::forecast(remaining, rate, period, period.start(today)) {
+            Some(date) => println!(
+                "At {:.1} tasks per {}, the backlog would be cleared around {}",
+                rate, period, date
+            ),
+            None => println!("No tasks finished recently, cannot forecast"),
+        }
+        Ok(())
+    }
+
+    fn stale(&self, thresholds: stale::Thresholds) -> Result<()> {
+        let index = index::Index::load().wrap_err("loading index")?;
+        let now = chrono::Utc::now();
+        let mut stale: Vec<_> = index
+            .tasks
+            .iter()
+            .filter_map(|t| thresholds.stale_age(t, now).map(|age| (age, t)))
+            .collect();
+        stale.sort_by_key(|(age, t)| (std::cmp::Reverse(*age), t.id));
+
+        if stale.is_empty() {
+            println!("no stale tasks");
+        }
+        for (age, task) in stale {
+            let detail = task
+                .detail()
+                .wrap_err_with(|| format!("reading task detail for task {}", task.id))?;
+            println!(
+                "{:03}: {:<6}{:>8}  {}",
+                task.id,
+                task.status.to_string(),
+                dates::format_duration(age),
+                detail.summary
+            );
+        }
+        Ok(())
+    }
+
+    fn standup(&self, since: String, author: Option<String>) -> Result<()> {
+        let since = dates::parse_since(&since).wrap_err("parsing start of window")?;
+        let index = index::Index::load().wrap_err("loading index")?;
+        let storage = storage::Storage::current()?;
+        let attribution = standup::Attribution::load(storage, Some(since))
+            .wrap_err("attributing changes to authors")?;
+        let who = author.as_deref().map(git::resolve_who).transpose()?;
+        let current_user = git::user_ident()?;
+
+        // changes without a recorded user or commit are uncommitted, so they were made
+        // by the current user
+        let matches_author = |event: &standup::Event| match &who {
+            Some(who) => attribution
+                .author(event)
+                .or_else(|| current_user.clone())
+                .map(|a| a.to_lowercase().contains(&who.to_lowercase()))
+                .unwrap_or(false),
+            None => true,
+        };
+
+        let window = dates::Window {
+            since: Some(since),
+            until: None,
+        };
+        let events: Vec<_> = standup::events(&index.all_tasks(), window)
+            .into_iter()
+            .filter(|e| matches_author(e))
+            .collect();
+
+        println!("Since {}", since.format("%Y-%m-%d %H:%M"));
+        for heading in standup::HEADINGS.iter() {
+            println!();
+            println!("{}", heading);
+            let mut found = false;
+            for event in events.iter().filter(|e| e.kind.heading() == *heading) {
+                found = true;
+                let summary = match index.find_task(event.task_id) {
+                    Some(task) => task.detail()?.summary,
+                    None => "<deleted task>".to_string(),
+                };
+                let mut line = format!("{:03}: {}", event.task_id, summary);
+                if let standup::EventKind::Moved { from, to } = &event.kind {
+                    line.push_str(&format!(" ({} -> {})", from, to));
+                }
+                match attribution.author(event) {
+                    Some(author) => line.push_str(&format!("  [{}]", standup::short_name(&author))),
+                    None => line.push_str("  [uncommitted]"),
+                }
+                println!("{}", line);
+            }
+            if !found {
+                println!("... nothing");
+            }
+        }
         Ok(())
     }
 
@@ -182,6 +1490,153 @@ impl<'a> Manager<'a> {
     }
 }
 
+/// Print the cards in a board column, nesting subtasks under their parent when the
+/// parent is in the same column
+fn print_cards(cards: &[(index::Task, String)], parent: Option<u64>, depth: usize) {
+    for (task, line) in cards {
+        let shown_parent = task
+            .parent
+            .filter(|p| cards.iter().any(|(t, _)| t.id == *p));
+        if shown_parent != parent {
+            continue;
+        }
+        match (parent, task.parent) {
+            (None, Some(other)) => println!("{} (part of {:03})", line, other),
+            _ => println!("{}{}", "    ".repeat(depth), line),
+        }
+        print_cards(cards, Some(task.id), depth + 1);
+    }
+}
+
+/// Ask a yes or no question on the terminal, defaulting to no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush().wrap_err("flushing stdout")?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .wrap_err("reading answer")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Print a section listing tasks by id, with their summary and status
+fn print_references(index: &index::Index, heading: &str, ids: &[u64]) -> Result<()> {
+    println!("{}", heading);
+    println!("{}", "-".repeat(heading.len()));
+    for id in ids {
+        match index.find_task(*id) {
+            Some(task) => {
+                let detail = task.detail().wrap_err("fetching task detail")?;
+                println!("#{}  {}  ({})", id, detail.summary.trim(), task.status);
+            }
+            None => println!("#{}  (deleted)", id),
+        }
+    }
+    Ok(())
+}
+
+/// List task ids along with their status, e.g. `001 (Done), 002 (Todo)`
+fn format_ids(index: &index::Index, ids: &[u64]) -> String {
+    let ids: Vec<_> = ids
+        .iter()
+        .map(|id| match index.find_task(*id) {
+            Some(task) => format!("{:03} ({})", id, task.status),
+            None => format!("{:03} (missing)", id),
+        })
+        .collect();
+    ids.join(", ")
+}
+
+/// Summarise the estimate of a task against the time logged on it
+fn format_effort(
+    task: &index::Task,
+    detail: &index::TaskDetail,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<String> {
+    let worked = task.time_worked(now);
+    let mut out = match (detail.estimate, worked) {
+        (None, None) => return None,
+        (Some(estimate), None) => format!("Estimate {}, no time logged", estimate),
+        (None, Some(worked)) => format!("Logged {}", dates::format_duration(worked)),
+        (Some(estimate), Some(worked)) => {
+            let mut out = format!(
+                "Estimate {}, logged {}",
+                estimate,
+                dates::format_duration(worked)
+            );
+            if let Some(estimated) = estimate.duration().filter(|d| !d.is_zero()) {
+                let percent = 100 * worked.num_seconds() / estimated.num_seconds();
+                out.push_str(&format!(" ({}% of estimate)", percent));
+            }
+            out
+        }
+    };
+    let running = task.work.iter().filter(|w| w.end.is_none()).count();
+    if running > 0 {
+        out.push_str(&format!(", {} timer(s) running", running));
+    }
+    Some(out)
+}
+
+/// Print a task's discussion thread
+fn print_comments(comments: &[comment::Comment]) {
+    let now = chrono::Utc::now();
+    println!("Comments");
+    println!("--------");
+    for (i, comment) in comments.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let by = comment
+            .by
+            .as_deref()
+            .map(standup::short_name)
+            .unwrap_or("unknown");
+        println!(
+            "{}, {} ({}):",
+            by,
+            comment.on.format("%Y-%m-%d %H:%M"),
+            dates::format_ago(comment.on, now)
+        );
+        for line in comment.text.trim().lines() {
+            println!("    {}", line);
+        }
+    }
+}
+
+/// Print each status change of a task, and the total time spent in each status
+fn print_history(task: &index::Task) {
+    let now = chrono::Utc::now();
+    println!("History");
+    println!("-------");
+    for entry in history::entries(task, now) {
+        let change = entry.change;
+        let mut line = format!(
+            "{} ({})  {} -> {}",
+            change.on.format("%Y-%m-%d %H:%M"),
+            dates::format_ago(change.on, now),
+            change.from,
+            change.to
+        );
+        if change.to != index::Status::Done {
+            line.push_str(&format!(", for {}", dates::format_duration(entry.spent)));
+        }
+        if let Some(by) = &change.by {
+            line.push_str(&format!("  by {}", by));
+        }
+        println!("{}", line);
+    }
+
+    let totals: Vec<_> = history::time_in_status(task, now)
+        .into_iter()
+        .map(|(status, spent)| format!("{} {}", status, dates::format_duration(spent)))
+        .collect();
+    if !totals.is_empty() {
+        println!();
+        println!("Time in status: {}", totals.join(", "));
+    }
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
     env_logger::init();
@@ -190,18 +1645,108 @@ fn main() -> Result<()> {
     let highlighter = highlighter::Highlighter::new("base16-eighties.dark");
     let mut manager = Manager { highlighter };
 
+    let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    // undo and redo replay the journal rather than adding to it
+    let record = !matches!(args, Opts::Undo | Opts::Redo);
+    if record {
+        journal::begin(command.clone());
+    }
+    let result = run(&mut manager, args);
+    // whatever a failed command managed to change is kept, as it is in the working tree,
+    // so that it can still be undone
+    let committed = storage::commit(&command).wrap_err("committing board");
+    if record {
+        let finished = journal::finish().wrap_err("recording in journal");
+        result?;
+        committed?;
+        return finished;
+    }
+    result?;
+    committed
+}
+
+fn run(manager: &mut Manager<'_>, args: Opts) -> Result<()> {
     match args {
-        Opts::Init { name, force } => manager.init(name, force).wrap_err("init")?,
-        Opts::Add { entry } => manager.add(entry).wrap_err("add")?,
-        Opts::Show { task_id } => manager.show(task_id).wrap_err("show")?,
-        Opts::Move { task_id, status } => manager.move_task(task_id, status).wrap_err("move")?,
-        Opts::Delete { task_id } => manager.delete_task(task_id).wrap_err("deleting")?,
+        Opts::Init {
+            name,
+            force,
+            storage,
+        } => manager.init(name, force, storage).wrap_err("init")?,
+        Opts::Add { entry, parent } => manager.add(entry, parent).wrap_err("add")?,
+        Opts::Show {
+            task_id,
+            stale,
+            mine,
+            overdue,
+            due_before,
+            milestone,
+            archived,
+        } => {
+            let today = chrono::Utc::now().naive_utc().date();
+            let due_before = due_before
+                .map(|day| {
+                    dates::parse_day(&day, today).ok_or_else(|| eyre::eyre!("invalid date {}", day))
+                })
+                .transpose()?;
+            let assigned_to = if mine {
+                Some(git::user_aliases()?)
+            } else {
+                None
+            };
+            let options = ShowOptions {
+                stale: stale::Thresholds::parse(&stale).wrap_err("parsing stale thresholds")?,
+                assigned_to,
+                due_before,
+                overdue,
+                milestone,
+                archived,
+            };
+            manager.show_with(task_id, &options).wrap_err("show")?
+        }
+        Opts::Move {
+            task_id,
+            status,
+            force,
+        } => manager.move_task(task_id, status, force).wrap_err("move")?,
+        Opts::Delete { task_id, hard } => {
+            manager.delete_task(task_id, hard).wrap_err("deleting")?
+        }
+        Opts::Restore { task_id } => manager.restore_task(task_id).wrap_err("restoring")?,
         Opts::Edit { task_id } => manager.edit_task(task_id).wrap_err("editing")?,
-        Opts::Start { task_id } => manager
-            .move_task(task_id, index::Status::Doing)
+        Opts::Log { task_id } => manager.log(task_id).wrap_err("showing history")?,
+        Opts::Due { task_id, day } => manager
+            .set_date(task_id, index::DateKind::Due, day)
+            .wrap_err("setting due date")?,
+        Opts::Schedule { task_id, day } => manager
+            .set_date(task_id, index::DateKind::Scheduled, day)
+            .wrap_err("scheduling task")?,
+        Opts::Depend {
+            task_id,
+            blocker_id,
+            remove,
+            ..
+        } => manager
+            .depend(task_id, blocker_id, remove)
+            .wrap_err("updating dependencies")?,
+        Opts::Graph { dot } => manager.graph(dot).wrap_err("printing graph")?,
+        Opts::Attach { task_id, file } => manager.attach(task_id, file).wrap_err("attaching")?,
+        Opts::Comment { task_id, text } => manager.comment(task_id, text).wrap_err("commenting")?,
+        Opts::Estimate { task_id, estimate } => manager
+            .set_estimate(task_id, estimate)
+            .wrap_err("setting estimate")?,
+        Opts::Timer(command) => manager.timer(command).wrap_err("timer")?,
+        Opts::Milestone(command) => manager.milestone(command).wrap_err("milestone")?,
+        Opts::Sprint(command) => manager.sprint(command).wrap_err("sprint")?,
+        Opts::Trash(command) => manager.trash(command).wrap_err("trash")?,
+        Opts::Assign { task_id, who } => manager.assign(task_id, who).wrap_err("assigning")?,
+        Opts::Unassign { task_id, who } => {
+            manager.unassign(task_id, who).wrap_err("unassigning")?
+        }
+        Opts::Start { task_id, force } => manager
+            .move_task(task_id, index::Status::Doing, force)
             .wrap_err("starting task")?,
         Opts::Finish { task_id } => manager
-            .move_task(task_id, index::Status::Done)
+            .move_task(task_id, index::Status::Done, false)
             .wrap_err("finishing task")?,
         Opts::Inc { task_id } => manager
             .update_task_priority(task_id, index::Priority::Increase)
@@ -209,6 +1754,46 @@ fn main() -> Result<()> {
         Opts::Dec { task_id } => manager
             .update_task_priority(task_id, index::Priority::Decrease)
             .wrap_err("decreasing task priority")?,
+        Opts::Limit { status, limit } => manager
+            .set_wip_limit(status, limit)
+            .wrap_err("setting limit")?,
+        Opts::Diff { range, textconv } => manager.diff(range, textconv).wrap_err("diff")?,
+        Opts::Changelog { since, append } => manager
+            .changelog(since, append)
+            .wrap_err("generating changelog")?,
+        Opts::Stats {
+            since,
+            until,
+            by,
+            json,
+        } => {
+            let window = dates::Window {
+                since: since.as_deref().map(dates::parse_since).transpose()?,
+                until: until.as_deref().map(dates::parse_since).transpose()?,
+            };
+            manager
+                .stats(window, by, json)
+                .wrap_err("computing stats")?
+        }
+        Opts::Stale { older_than } => {
+            let thresholds = stale::Thresholds::parse(&older_than)
+                .wrap_err("parsing stale thresholds")?
+                .or_fallback(chrono::Duration::days(7));
+            manager.stale(thresholds).wrap_err("finding stale tasks")?
+        }
+        Opts::Standup { since, author } => {
+            manager.standup(since, author).wrap_err("standup report")?
+        }
+        Opts::Throughput { per, window } => manager
+            .throughput(per, window)
+            .wrap_err("computing throughput")?,
+        Opts::Chart { kind, since, svg } => manager.chart(kind, since, svg).wrap_err("charting")?,
+        Opts::Fsck => manager.fsck().wrap_err("checking board")?,
+        Opts::Archive { done_before } => manager.archive(done_before).wrap_err("archiving")?,
+        Opts::Unarchive { task_ids } => manager.unarchive(task_ids).wrap_err("unarchiving")?,
+        Opts::Scan { create } => manager.scan(create).wrap_err("scanning")?,
+        Opts::Undo => manager.undo().wrap_err("undoing")?,
+        Opts::Redo => manager.redo().wrap_err("redoing")?,
     }
 
     Ok(())
@@ -0,0 +1,173 @@
+use crate::index::{Progress, Status, Task};
+use crate::throughput::{self, Period};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The number of trailing weeks of throughput used to project completion
+const TRAILING_WEEKS: usize = 4;
+
+/// A named goal which tasks can be grouped under, declared in the index `meta`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Milestone {
+    pub name: String,
+    /// The day the milestone should be reached by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// How far along a milestone is and when it is likely to be reached
+#[derive(Debug)]
+pub struct Report<'a> {
+    pub milestone: &'a Milestone,
+    pub progress: Progress,
+    /// The unfinished tasks, most advanced first
+    pub remaining: Vec<&'a Task>,
+    /// Tasks finished per week recently, across the whole board
+    pub rate: f64,
+    /// When the remaining tasks would be finished at the recent rate
+    pub projected: Option<NaiveDate>,
+}
+
+impl<'a> Report<'a> {
+    /// Build a report for `milestone` from all the tasks on the board, using the
+    /// throughput of the whole board to project completion
+    pub fn new(milestone: &'a Milestone, tasks: &'a [Task], today: NaiveDate) -> Report<'a> {
+        let in_milestone: Vec<&Task> = tasks
+            .iter()
+            .filter(|t| t.milestone.as_deref() == Some(milestone.name.as_str()))
+            .collect();
+        let mut remaining: Vec<&Task> = in_milestone
+            .iter()
+            .copied()
+            .filter(|t| t.status != Status::Done)
+            .collect();
+        remaining.sort_by_key(|t| (std::cmp::Reverse(t.status), t.id));
+
+        let buckets = throughput::buckets(tasks, Period::Week, TRAILING_WEEKS, today);
+        let rate = throughput::rate(&buckets, Period::Week, today);
+        let projected = if remaining.is_empty() {
+            None
+        } else {
+            throughput::forecast(
+                remaining.len(),
+                rate,
+                Period::Week,
+                Period::Week.start(today),
+            )
+        };
+
+        Report {
+            milestone,
+            progress: Progress {
+                done: in_milestone.len() - remaining.len(),
+                total: in_milestone.len(),
+            },
+            remaining,
+            rate,
+            projected,
+        }
+    }
+
+    /// How many days after the target the milestone is projected to be reached, or
+    /// a negative number if it is projected to be early
+    pub fn slip(&self) -> Option<i64> {
+        let target = self.milestone.target?;
+        let projected = self.projected?;
+        Some((projected - target).num_days())
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.milestone.name)?;
+        writeln!(f, "{}", "-".repeat(self.milestone.name.len()))?;
+        if !self.milestone.description.is_empty() {
+            writeln!(f, "{}", self.milestone.description.trim())?;
+            writeln!(f)?;
+        }
+        if let Some(target) = self.milestone.target {
+            writeln!(f, "Target:    {}", target)?;
+        }
+        writeln!(f, "Progress:  {} tasks done", self.progress)?;
+
+        if self.progress.total > 0 && self.remaining.is_empty() {
+            writeln!(f, "Complete")?;
+        } else if let Some(projected) = self.projected {
+            let mut line = format!(
+                "Projected: {} at {:.1} tasks per week",
+                projected, self.rate
+            );
+            match self.slip() {
+                Some(days) if days > 0 => line.push_str(&format!(", {} days late", days)),
+                Some(_) => line.push_str(", on track"),
+                None => {}
+            }
+            writeln!(f, "{}", line)?;
+        } else if !self.remaining.is_empty() {
+            writeln!(f, "Projected: no tasks finished recently, cannot forecast")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Change;
+    use chrono::{TimeZone, Utc};
+
+    fn task(id: u64, status: Status, milestone: Option<&str>, finished: Option<u32>) -> Task {
+        Task {
+            id,
+            status,
+            changes: finished
+                .map(|day| Change {
+                    from: Status::Doing,
+                    to: Status::Done,
+                    on: Utc.with_ymd_and_hms(2021, 9, day, 12, 0, 0).unwrap(),
+                    by: None,
+                })
+                .into_iter()
+                .collect(),
+            milestone: milestone.map(|m| m.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn milestone_progress_and_projection() {
+        let milestone = Milestone {
+            name: "v1.0".to_string(),
+            target: Some(NaiveDate::from_ymd_opt(2021, 9, 27).unwrap()),
+            description: String::new(),
+        };
+        // eight tasks finished over the four weeks up to 2021-09-22, seven of them in
+        // the three weeks before the current one
+        let mut tasks: Vec<_> = (0..8)
+            .map(|i| task(i + 1, Status::Done, None, Some(1 + i as u32 * 3)))
+            .collect();
+        tasks.push(task(9, Status::Done, Some("v1.0"), None));
+        tasks.push(task(10, Status::Todo, Some("v1.0"), None));
+        tasks.push(task(11, Status::Doing, Some("v1.0"), None));
+        tasks.push(task(12, Status::Todo, Some("v1.0"), None));
+        tasks.push(task(13, Status::Todo, Some("v2.0"), None));
+
+        let report = Report::new(
+            &milestone,
+            &tasks,
+            NaiveDate::from_ymd_opt(2021, 9, 22).unwrap(),
+        );
+        assert_eq!(report.progress, Progress { done: 1, total: 4 });
+        let remaining: Vec<_> = report.remaining.iter().map(|t| t.id).collect();
+        assert_eq!(remaining, vec![11, 10, 12]);
+        assert_eq!(report.rate, 7.0 / 3.0);
+        assert_eq!(
+            report.projected,
+            Some(NaiveDate::from_ymd_opt(2021, 10, 4).unwrap())
+        );
+        assert_eq!(report.slip(), Some(7));
+    }
+}
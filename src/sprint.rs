@@ -0,0 +1,175 @@
+use crate::chart::{self, Day};
+use crate::index::Task;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// A fixed period of work, declared in the index `meta`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sprint {
+    pub name: String,
+    pub start: NaiveDate,
+    /// The last day of the sprint
+    pub end: NaiveDate,
+    /// The tasks planned into the sprint, including any rolled over from the previous
+    /// sprint
+    #[serde(default)]
+    pub tasks: Vec<u64>,
+    #[serde(default)]
+    pub closed: bool,
+}
+
+impl Sprint {
+    pub fn new(name: String, start: NaiveDate, length: Duration) -> Sprint {
+        Sprint {
+            name,
+            start,
+            end: start + length - Duration::days(1),
+            tasks: Vec::new(),
+            closed: false,
+        }
+    }
+
+    pub fn length(&self) -> Duration {
+        self.end - self.start + Duration::days(1)
+    }
+
+    /// The sprint following this one, of the same length. It starts the day after
+    /// this one ends, or `today` if the sprint was closed late.
+    pub fn next(&self, name: String, today: NaiveDate) -> Sprint {
+        Sprint::new(
+            name,
+            (self.end + Duration::days(1)).max(today),
+            self.length(),
+        )
+    }
+
+    /// The tasks remaining in the sprint at the end of each day, up to the end of the
+    /// sprint or `today` if the sprint is still running
+    pub fn burndown(&self, tasks: &[Task], today: NaiveDate) -> Vec<Day> {
+        let tasks: Vec<Task> = tasks
+            .iter()
+            .filter(|t| self.tasks.contains(&t.id))
+            .cloned()
+            .collect();
+        if today < self.start {
+            return Vec::new();
+        }
+        chart::daily_counts(&tasks, self.start, self.end.min(today))
+    }
+}
+
+/// The default name for a new sprint, numbering on from the existing sprints and
+/// skipping any name already taken
+pub fn next_name(sprints: &[Sprint]) -> String {
+    (sprints.len() + 1..)
+        .map(|n| format!("Sprint {}", n))
+        .find(|name| !sprints.iter().any(|s| &s.name == name))
+        // unwrap is safe because there are fewer sprints than names to try
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{Change, Status};
+    use chrono::{TimeZone, Utc};
+
+    fn task(id: u64, changes: &[(Status, u32)]) -> Task {
+        let mut from = Status::None;
+        let changes = changes
+            .iter()
+            .map(|(to, day)| {
+                let change = Change {
+                    from,
+                    to: *to,
+                    on: Utc.with_ymd_and_hms(2021, 9, *day, 12, 0, 0).unwrap(),
+                    by: None,
+                };
+                from = *to;
+                change
+            })
+            .collect();
+        Task {
+            id,
+            status: from,
+            changes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn next_sprint() {
+        let sprint = Sprint::new(
+            "Sprint 1".to_string(),
+            NaiveDate::from_ymd_opt(2021, 9, 13).unwrap(),
+            Duration::weeks(2),
+        );
+        assert_eq!(sprint.end, NaiveDate::from_ymd_opt(2021, 9, 26).unwrap());
+
+        let next = sprint.next(
+            "Sprint 2".to_string(),
+            NaiveDate::from_ymd_opt(2021, 9, 24).unwrap(),
+        );
+        assert_eq!(next.start, NaiveDate::from_ymd_opt(2021, 9, 27).unwrap());
+        assert_eq!(next.end, NaiveDate::from_ymd_opt(2021, 10, 10).unwrap());
+
+        let late = sprint.next(
+            "Sprint 2".to_string(),
+            NaiveDate::from_ymd_opt(2021, 9, 29).unwrap(),
+        );
+        assert_eq!(late.start, NaiveDate::from_ymd_opt(2021, 9, 29).unwrap());
+    }
+
+    #[test]
+    fn sprint_burndown() {
+        let mut sprint = Sprint::new(
+            "Sprint 1".to_string(),
+            NaiveDate::from_ymd_opt(2021, 9, 13).unwrap(),
+            Duration::days(3),
+        );
+        sprint.tasks = vec![1, 2];
+        let tasks = vec![
+            task(1, &[(Status::Todo, 1), (Status::Done, 14)]),
+            task(2, &[(Status::Todo, 1), (Status::Doing, 13)]),
+            task(3, &[(Status::Todo, 1)]),
+        ];
+
+        let remaining: Vec<_> = sprint
+            .burndown(&tasks, NaiveDate::from_ymd_opt(2021, 9, 30).unwrap())
+            .iter()
+            .map(|d| d.remaining())
+            .collect();
+        assert_eq!(remaining, vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn next_name_skips_taken_names() {
+        let start = NaiveDate::from_ymd_opt(2021, 9, 1).unwrap();
+        let mut sprints = vec![Sprint::new(
+            "Sprint 1".to_string(),
+            start,
+            Duration::weeks(2),
+        )];
+        assert_eq!(next_name(&sprints), "Sprint 2");
+
+        sprints.push(Sprint::new(
+            "Sprint 2".to_string(),
+            start,
+            Duration::weeks(2),
+        ));
+        sprints.push(Sprint::new(
+            "Sprint 4".to_string(),
+            start,
+            Duration::weeks(2),
+        ));
+        assert_eq!(next_name(&sprints), "Sprint 5");
+
+        sprints[1].name = "Sprint 3".to_string();
+        sprints.push(Sprint::new(
+            "Hardening".to_string(),
+            start,
+            Duration::weeks(1),
+        ));
+        assert_eq!(next_name(&sprints), "Sprint 5");
+    }
+}
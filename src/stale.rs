@@ -0,0 +1,113 @@
+use crate::dates::parse_duration;
+use crate::index::{Status, Task};
+use chrono::{DateTime, Duration, Utc};
+use eyre::{Result, WrapErr};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default)]
+pub struct Thresholds {
+    fallback: Option<Duration>,
+    per_status: BTreeMap<Status, Duration>,
+}
+
+impl Thresholds {
+    pub fn parse(specs: &[String]) -> Result<Thresholds> {
+        let mut thresholds = Thresholds::default();
+        for spec in specs {
+            match spec.split_once('=') {
+                Some((status, duration)) => {
+                    let status: Status = status.parse()?;
+                    let duration = parse_duration(duration)
+                        .wrap_err_with(|| format!("parsing threshold for {}", status))?;
+                    thresholds.per_status.insert(status, duration);
+                }
+                None => thresholds.fallback = Some(parse_duration(spec)?),
+            }
+        }
+        Ok(thresholds)
+    }
+
+    pub fn or_fallback(mut self, fallback: Duration) -> Thresholds {
+        self.fallback.get_or_insert(fallback);
+        self
+    }
+
+    pub fn stale_age(&self, task: &Task, now: DateTime<Utc>) -> Option<Duration> {
+        let threshold = match self.per_status.get(&task.status) {
+            Some(threshold) => threshold,
+            None if matches!(task.status, Status::Todo | Status::Doing) => {
+                self.fallback.as_ref()?
+            }
+            None => return None,
+        };
+        let age = now - task.last_changed()?;
+        if age > *threshold {
+            Some(age)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Change;
+    use chrono::TimeZone;
+
+    fn task(status: Status, on: DateTime<Utc>) -> Task {
+        Task {
+            id: 1,
+            status,
+            changes: vec![Change {
+                from: Status::None,
+                to: status,
+                on,
+                by: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stale_tasks() {
+        let now = Utc.with_ymd_and_hms(2021, 9, 30, 0, 0, 0).unwrap();
+        let thresholds = Thresholds::parse(&["7d".to_string(), "doing=2d".to_string()]).unwrap();
+
+        let old_todo = task(Status::Todo, now - Duration::days(8));
+        let recent_todo = task(Status::Todo, now - Duration::days(6));
+        let old_doing = task(Status::Doing, now - Duration::days(3));
+        let old_done = task(Status::Done, now - Duration::days(30));
+
+        assert_eq!(
+            thresholds.stale_age(&old_todo, now),
+            Some(Duration::days(8))
+        );
+        assert_eq!(thresholds.stale_age(&recent_todo, now), None);
+        assert_eq!(
+            thresholds.stale_age(&old_doing, now),
+            Some(Duration::days(3))
+        );
+        assert_eq!(thresholds.stale_age(&old_done, now), None);
+    }
+
+    #[test]
+    fn per_status_thresholds_win_in_any_order() {
+        let now = Utc.with_ymd_and_hms(2021, 9, 30, 0, 0, 0).unwrap();
+        let doing = task(Status::Doing, now - Duration::days(5));
+        let todo = task(Status::Todo, now - Duration::days(10));
+
+        for specs in [["doing=3d", "14d"], ["14d", "doing=3d"]] {
+            let specs: Vec<String> = specs.iter().map(|s| s.to_string()).collect();
+            let thresholds = Thresholds::parse(&specs).unwrap();
+            assert_eq!(thresholds.stale_age(&doing, now), Some(Duration::days(5)));
+            assert_eq!(thresholds.stale_age(&todo, now), None);
+        }
+
+        let thresholds = Thresholds::parse(&["doing=3d".to_string()])
+            .unwrap()
+            .or_fallback(Duration::days(7));
+        assert_eq!(thresholds.stale_age(&todo, now), Some(Duration::days(10)));
+        assert_eq!(thresholds.stale_age(&doing, now), Some(Duration::days(5)));
+    }
+}
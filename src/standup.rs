@@ -0,0 +1,173 @@
+use crate::dates::Window;
+use crate::git::{self, Commit};
+use crate::index::{self, Index, Status, Task};
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use eyre::{Result, WrapErr};
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub enum EventKind {
+    Added,
+    Moved { from: Status, to: Status },
+    Finished,
+}
+
+/// The report sections, in display order
+pub const HEADINGS: [&str; 3] = ["Finished", "Moved", "Added"];
+
+impl EventKind {
+    /// The report section the event is listed under
+    pub fn heading(&self) -> &'static str {
+        match self {
+            EventKind::Added => "Added",
+            EventKind::Moved { .. } => "Moved",
+            EventKind::Finished => "Finished",
+        }
+    }
+}
+
+/// Something that happened to a task during the standup window
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub task_id: u64,
+    pub on: DateTime<Utc>,
+    pub kind: EventKind,
+    /// Who made the change, if it was recorded
+    pub by: Option<String>,
+}
+
+/// Collect the status changes within `window`, oldest first
+pub fn events(tasks: &[Task], window: Window) -> Vec<Event> {
+    let mut events: Vec<Event> = tasks
+        .iter()
+        .flat_map(|task| {
+            task.changes
+                .iter()
+                .filter(|c| window.contains(c.on))
+                .map(move |c| Event {
+                    task_id: task.id,
+                    on: c.on,
+                    kind: match (c.from, c.to) {
+                        (Status::None, _) => EventKind::Added,
+                        (_, Status::Done) => EventKind::Finished,
+                        (from, to) => EventKind::Moved { from, to },
+                    },
+                    by: c.by.clone(),
+                })
+        })
+        .collect();
+    events.sort_by_key(|e| (e.on, e.task_id));
+    events
+}
+
+/// Who committed each status change, found by comparing each commit touching the index
+/// with its parent
+#[derive(Debug, Default)]
+pub struct Attribution(HashMap<(u64, DateTime<Utc>), Commit>);
+
+impl Attribution {
+    pub fn load(storage: Storage, since: Option<DateTime<Utc>>) -> Result<Attribution> {
+        let rev = storage.default_rev();
+        if git::resolve(rev)?.is_none() {
+            return Ok(Attribution::default());
+        }
+
+        let path = storage.repo_path(index::INDEX_FILE);
+        let mut attribution = HashMap::new();
+        for commit in git::log(rev, &[path], since).wrap_err("listing index commits")? {
+            let after = match storage.read_at(&commit.id, index::INDEX_FILE)? {
+                Some(contents) => Index::parse(&contents)?,
+                None => continue,
+            };
+            let before = match storage.read_at(&format!("{}^", commit.id), index::INDEX_FILE)? {
+                Some(contents) => Some(Index::parse(&contents)?),
+                None => None,
+            };
+
+            for task in &after.tasks {
+                let previous = before.as_ref().and_then(|b| b.get_task(task.id));
+                for change in &task.changes {
+                    let existed = previous
+                        .map(|p| p.changes.iter().any(|c| c.on == change.on))
+                        .unwrap_or(false);
+                    if !existed {
+                        attribution.insert((task.id, change.on), commit.clone());
+                    }
+                }
+            }
+        }
+        Ok(Attribution(attribution))
+    }
+
+    /// Who made an event: the recorded user if there is one, otherwise the author of
+    /// the commit which recorded it. `None` if neither is known.
+    pub fn author(&self, event: &Event) -> Option<String> {
+        event.by.clone().or_else(|| {
+            self.0
+                .get(&(event.task_id, event.on))
+                .map(|c| format!("{} <{}>", c.author_name, c.author_email))
+        })
+    }
+}
+
+/// The name part of a `Name <email>` user
+pub fn short_name(ident: &str) -> &str {
+    match ident.find(" <") {
+        Some(idx) => &ident[..idx],
+        None => ident,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Change;
+    use chrono::TimeZone;
+
+    #[test]
+    fn events_within_window() {
+        let on = |day| Utc.with_ymd_and_hms(2021, 9, day, 12, 0, 0).unwrap();
+        let tasks = vec![Task {
+            id: 1,
+            status: Status::Done,
+            changes: vec![
+                Change {
+                    from: Status::None,
+                    to: Status::Todo,
+                    on: on(1),
+                    by: None,
+                },
+                Change {
+                    from: Status::Todo,
+                    to: Status::Doing,
+                    on: on(2),
+                    by: None,
+                },
+                Change {
+                    from: Status::Doing,
+                    to: Status::Done,
+                    on: on(3),
+                    by: None,
+                },
+            ],
+            ..Default::default()
+        }];
+        let window = Window {
+            since: Some(on(2)),
+            until: None,
+        };
+
+        let kinds: Vec<_> = events(&tasks, window).into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::Moved {
+                    from: Status::Todo,
+                    to: Status::Doing
+                },
+                EventKind::Finished
+            ]
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use crate::dates::{format_duration, Window};
+use crate::estimate::Estimate;
+use crate::index::{Task, TaskDetail};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+fn hours<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(duration.num_seconds() as f64 / 3600.0)
+}
+
+fn optional_hours<S: Serializer>(duration: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => hours(duration, s),
+        None => s.serialize_none(),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskTimes {
+    pub id: u64,
+    pub summary: String,
+    pub tags: Vec<String>,
+    pub finished_on: DateTime<Utc>,
+    pub due: Option<NaiveDate>,
+    #[serde(rename = "lead_time_hours", serialize_with = "hours")]
+    pub lead_time: Duration,
+    #[serde(rename = "cycle_time_hours", serialize_with = "optional_hours")]
+    pub cycle_time: Option<Duration>,
+    pub estimate: Option<Estimate>,
+    #[serde(rename = "worked_hours", serialize_with = "optional_hours")]
+    pub worked: Option<Duration>,
+}
+
+impl TaskTimes {
+    pub fn new(task: &Task, detail: &TaskDetail) -> Option<TaskTimes> {
+        let finished_on = task.finished_on()?;
+        let created_on = task.created_on()?;
+        Some(TaskTimes {
+            id: task.id,
+            summary: detail.summary.clone(),
+            tags: detail.tags.clone(),
+            finished_on,
+            due: task.due,
+            lead_time: finished_on - created_on,
+            cycle_time: task.started_on().map(|s| finished_on - s),
+            estimate: detail.estimate,
+            worked: task.time_worked(finished_on),
+        })
+    }
+
+    pub fn actual(&self) -> Option<Duration> {
+        self.worked.or(self.cycle_time)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Accuracy {
+    pub hours_estimated: usize,
+    pub mean_ratio: Option<f64>,
+    pub median_ratio: Option<f64>,
+    pub points_estimated: usize,
+    pub hours_per_point: Option<f64>,
+}
+
+impl Accuracy {
+    pub fn new(tasks: &[TaskTimes]) -> Accuracy {
+        let mut ratios = Vec::new();
+        let (mut points, mut point_hours) = (Vec::new(), 0.0);
+        for task in tasks {
+            let actual = match task.actual() {
+                Some(actual) => actual.num_seconds() as f64 / 3600.0,
+                None => continue,
+            };
+            match task.estimate {
+                Some(Estimate::Hours(hours)) if hours > 0.0 => ratios.push(actual / hours),
+                Some(Estimate::Points(p)) if p > 0.0 => {
+                    points.push(p);
+                    point_hours += actual;
+                }
+                _ => {}
+            }
+        }
+
+        ratios.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let total_points: f64 = points.iter().sum();
+        Accuracy {
+            hours_estimated: ratios.len(),
+            mean_ratio: if ratios.is_empty() {
+                None
+            } else {
+                Some(ratios.iter().sum::<f64>() / ratios.len() as f64)
+            },
+            median_ratio: if ratios.is_empty() {
+                None
+            } else {
+                Some(ratios[(ratios.len() - 1) / 2])
+            },
+            points_estimated: points.len(),
+            hours_per_point: if points.is_empty() {
+                None
+            } else {
+                Some(point_hours / total_points)
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    #[serde(rename = "mean_hours", serialize_with = "hours")]
+    pub mean: Duration,
+    #[serde(rename = "p50_hours", serialize_with = "hours")]
+    pub p50: Duration,
+    #[serde(rename = "p85_hours", serialize_with = "hours")]
+    pub p85: Duration,
+    #[serde(rename = "p95_hours", serialize_with = "hours")]
+    pub p95: Duration,
+}
+
+impl Summary {
+    pub fn new(durations: &[Duration]) -> Option<Summary> {
+        if durations.is_empty() {
+            return None;
+        }
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+        let total = sorted.iter().fold(Duration::zero(), |acc, d| acc + *d);
+        Some(Summary {
+            count: sorted.len(),
+            mean: total / sorted.len() as i32,
+            p50: percentile(&sorted, 50),
+            p85: percentile(&sorted, 85),
+            p95: percentile(&sorted, 95),
+        })
+    }
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (p * sorted.len()).div_ceil(100);
+    sorted[rank.max(1) - 1]
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeSummaries {
+    pub lead_time: Option<Summary>,
+    pub cycle_time: Option<Summary>,
+}
+
+impl TimeSummaries {
+    fn new<'a>(tasks: impl Iterator<Item = &'a TaskTimes> + Clone) -> TimeSummaries {
+        let lead_times: Vec<_> = tasks.clone().map(|t| t.lead_time).collect();
+        let cycle_times: Vec<_> = tasks.filter_map(|t| t.cycle_time).collect();
+        TimeSummaries {
+            lead_time: Summary::new(&lead_times),
+            cycle_time: Summary::new(&cycle_times),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub tasks: Vec<TaskTimes>,
+    pub overall: TimeSummaries,
+    pub tags: BTreeMap<String, TimeSummaries>,
+    pub estimation: Accuracy,
+}
+
+impl Report {
+    pub fn new(tasks: Vec<TaskTimes>, window: Window) -> Report {
+        let mut tasks: Vec<_> = tasks
+            .into_iter()
+            .filter(|t| window.contains(t.finished_on))
+            .collect();
+        tasks.sort_by_key(|t| (t.finished_on, t.id));
+
+        let overall = TimeSummaries::new(tasks.iter());
+        let estimation = Accuracy::new(&tasks);
+        let mut tags = BTreeMap::new();
+        for tag in tasks.iter().flat_map(|t| t.tags.iter()) {
+            if tags.contains_key(tag) {
+                continue;
+            }
+            let tagged = tasks.iter().filter(|t| t.tags.contains(tag));
+            tags.insert(tag.clone(), TimeSummaries::new(tagged));
+        }
+
+        Report {
+            since: window.since,
+            until: window.until,
+            tasks,
+            overall,
+            tags,
+            estimation,
+        }
+    }
+}
+
+fn write_summary(
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    summary: &Option<Summary>,
+) -> fmt::Result {
+    match summary {
+        Some(s) => writeln!(
+            f,
+            "{:<12}{:>6}  {:>8}  {:>8}  {:>8}  {:>8}",
+            label,
+            s.count,
+            format_duration(s.mean),
+            format_duration(s.p50),
+            format_duration(s.p85),
+            format_duration(s.p95)
+        ),
+        None => writeln!(f, "{:<12}{:>6}", label, 0),
+    }
+}
+
+fn write_summaries(f: &mut fmt::Formatter<'_>, summaries: &TimeSummaries) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<12}{:>6}  {:>8}  {:>8}  {:>8}  {:>8}",
+        "", "count", "mean", "p50", "p85", "p95"
+    )?;
+    write_summary(f, "lead time", &summaries.lead_time)?;
+    write_summary(f, "cycle time", &summaries.cycle_time)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} tasks finished", self.tasks.len())?;
+        writeln!(f)?;
+        for task in &self.tasks {
+            let cycle_time = task
+                .cycle_time
+                .map(format_duration)
+                .unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "{:03}: lead {:>8}  cycle {:>8}  {}",
+                task.id,
+                format_duration(task.lead_time),
+                cycle_time,
+                task.summary
+            )?;
+        }
+        writeln!(f)?;
+        write_summaries(f, &self.overall)?;
+        for (tag, summaries) in &self.tags {
+            writeln!(f)?;
+            writeln!(f, ":{}:", tag)?;
+            write_summaries(f, summaries)?;
+        }
+
+        let estimation = &self.estimation;
+        if let (Some(mean), Some(median)) = (estimation.mean_ratio, estimation.median_ratio) {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{} tasks estimated in hours took {:.2}x the estimate on average, {:.2}x median",
+                estimation.hours_estimated, mean, median
+            )?;
+        }
+        if let Some(hours_per_point) = estimation.hours_per_point {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{} tasks estimated in points took {} per point",
+                estimation.points_estimated,
+                format_duration(Duration::seconds((hours_per_point * 3600.0) as i64))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarise_durations() {
+        let durations: Vec<_> = (1..=10).map(Duration::hours).collect();
+        let summary = Summary::new(&durations).unwrap();
+
+        assert_eq!(summary.count, 10);
+        assert_eq!(summary.mean, Duration::minutes(330));
+        assert_eq!(summary.p50, Duration::hours(5));
+        assert_eq!(summary.p85, Duration::hours(9));
+        assert_eq!(summary.p95, Duration::hours(10));
+    }
+
+    fn times(estimate: Option<Estimate>, worked: Option<Duration>) -> TaskTimes {
+        TaskTimes {
+            id: 1,
+            summary: String::new(),
+            tags: Vec::new(),
+            finished_on: Utc::now(),
+            due: None,
+            lead_time: Duration::hours(10),
+            cycle_time: Some(Duration::hours(8)),
+            estimate,
+            worked,
+        }
+    }
+
+    #[test]
+    fn estimation_accuracy() {
+        let tasks = vec![
+            times(Some(Estimate::Hours(2.0)), Some(Duration::hours(3))),
+            times(Some(Estimate::Hours(4.0)), None),
+            times(Some(Estimate::Hours(4.0)), Some(Duration::hours(4))),
+            times(Some(Estimate::Points(2.0)), Some(Duration::hours(6))),
+            times(None, Some(Duration::hours(1))),
+        ];
+        let accuracy = Accuracy::new(&tasks);
+
+        assert_eq!(accuracy.hours_estimated, 3);
+        assert_eq!(accuracy.mean_ratio, Some(1.5));
+        assert_eq!(accuracy.median_ratio, Some(1.5));
+        assert_eq!(accuracy.points_estimated, 1);
+        assert_eq!(accuracy.hours_per_point, Some(3.0));
+    }
+
+    #[test]
+    fn summarise_nothing() {
+        assert_eq!(Summary::new(&[]), None);
+    }
+}
@@ -0,0 +1,560 @@
+use crate::git;
+use crate::journal;
+use eyre::{Result, WrapErr};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// The ref holding the board when using ref storage
+pub const BOARD_REF: &str = "refs/pm/board";
+
+static CURRENT: OnceLock<Storage> = OnceLock::new();
+
+/// The board ref of this repository, holding the changes staged by the running command
+static REF_BOARD: Mutex<Option<RefBoard>> = Mutex::new(None);
+
+/// Where the board state (`index.yml` and the task files) is kept.
+///
+/// All paths given to a storage are relative to the board root, e.g. `index.yml` or
+/// `tasks/001.md`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    /// In the `pm` directory of the working tree
+    WorkTree,
+    /// As git objects on an orphan ref, shared across branches
+    Ref,
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Storage::WorkTree => write!(f, "worktree"),
+            Storage::Ref => write!(f, "ref"),
+        }
+    }
+}
+
+impl std::str::FromStr for Storage {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "worktree" => Ok(Storage::WorkTree),
+            "ref" => Ok(Storage::Ref),
+            other => Err(eyre::eyre!("invalid storage {}", other)),
+        }
+    }
+}
+
+impl Storage {
+    /// The storage in use for this repository.
+    ///
+    /// The `pm.storage` git config value takes precedence, otherwise a board in the
+    /// working tree is preferred over one on the board ref.
+    pub fn current() -> Result<Storage> {
+        if let Some(storage) = CURRENT.get() {
+            return Ok(*storage);
+        }
+
+        let storage = match git::config("pm.storage")? {
+            Some(value) => value.parse().wrap_err("parsing pm.storage config")?,
+            None => {
+                let in_work_tree = find_project_root()
+                    .map(|r| r.join("pm").join("index.yml").is_file())
+                    .unwrap_or(false);
+                if !in_work_tree && git::resolve(BOARD_REF)?.is_some() {
+                    Storage::Ref
+                } else {
+                    Storage::WorkTree
+                }
+            }
+        };
+        Ok(*CURRENT.get_or_init(|| storage))
+    }
+
+    /// Use this storage for the running command, whatever the git config says
+    pub fn select(storage: Storage) {
+        let _ = CURRENT.set(storage);
+    }
+
+    /// Record this storage as the one used by the repository in the git config
+    pub fn record(&self) -> Result<()> {
+        git::run(&["config", "pm.storage", &self.to_string()])
+            .wrap_err("setting pm.storage config")?;
+        Ok(())
+    }
+
+    /// Whether this storage already holds a board
+    pub fn has_board(&self) -> Result<bool> {
+        match self {
+            Storage::WorkTree => Ok(pm_dir()?.join("index.yml").is_file()),
+            Storage::Ref => Ok(git::resolve(BOARD_REF)?.is_some()),
+        }
+    }
+
+    /// The revision describing the latest committed state of the board
+    pub fn default_rev(&self) -> &'static str {
+        match self {
+            Storage::WorkTree => "HEAD",
+            Storage::Ref => BOARD_REF,
+        }
+    }
+
+    pub fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            Storage::WorkTree => {
+                let full_path = pm_dir()?.join(path);
+                if !full_path.is_file() {
+                    return Ok(None);
+                }
+                let contents = std::fs::read(&full_path)
+                    .wrap_err_with(|| format!("reading {:?}", &full_path))?;
+                Ok(Some(contents))
+            }
+            Storage::Ref => with_ref_board(|board| board.read(path)),
+        }
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Result<Option<String>> {
+        match self.read(path)? {
+            Some(contents) => {
+                let contents =
+                    String::from_utf8(contents).wrap_err_with(|| format!("decoding {}", path))?;
+                Ok(Some(contents))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Read a file as it was at revision `rev`
+    pub fn read_at(&self, rev: &str, path: &str) -> Result<Option<String>> {
+        match self {
+            Storage::WorkTree => git::show(rev, &self.repo_path(path)),
+            Storage::Ref => with_ref_board(|board| board.read_at(rev, path)),
+        }
+    }
+
+    /// The path of a board file within the commits of `default_rev`
+    pub fn repo_path(&self, path: &str) -> String {
+        match self {
+            Storage::WorkTree => format!("pm/{}", path),
+            Storage::Ref => path.to_string(),
+        }
+    }
+
+    pub fn exists(&self, path: &str) -> Result<bool> {
+        match self {
+            Storage::WorkTree => Ok(pm_dir()?.join(path).is_file()),
+            Storage::Ref => with_ref_board(|board| board.exists(path)),
+        }
+    }
+
+    /// The files under directory `dir` of the board, e.g. `attachments/001`
+    pub fn list(&self, dir: &str) -> Result<Vec<String>> {
+        match self {
+            Storage::WorkTree => {
+                let pm_dir = pm_dir()?;
+                let mut files = Vec::new();
+                let mut dirs = vec![pm_dir.join(dir)];
+                while let Some(d) = dirs.pop() {
+                    if !d.is_dir() {
+                        continue;
+                    }
+                    for entry in
+                        std::fs::read_dir(&d).wrap_err_with(|| format!("listing {:?}", d))?
+                    {
+                        let path = entry.wrap_err_with(|| format!("listing {:?}", d))?.path();
+                        if path.is_dir() {
+                            dirs.push(path);
+                        } else if let Ok(relative) = path.strip_prefix(&pm_dir) {
+                            let parts: Vec<_> =
+                                relative.iter().map(|p| p.to_string_lossy()).collect();
+                            files.push(parts.join("/"));
+                        }
+                    }
+                }
+                files.sort();
+                Ok(files)
+            }
+            Storage::Ref => with_ref_board(|board| board.list(dir)),
+        }
+    }
+
+    pub fn write(&self, path: &str, contents: &[u8]) -> Result<()> {
+        journal::before_change(self, path)?;
+        match self {
+            Storage::WorkTree => {
+                let full_path = pm_dir()?.join(path);
+                ensure_parent_dir(&full_path)
+                    .wrap_err_with(|| format!("ensuring parent dir for path {:?}", full_path))?;
+                std::fs::write(&full_path, contents)
+                    .wrap_err_with(|| format!("writing {:?}", &full_path))?;
+            }
+            Storage::Ref => with_ref_board(|board| board.write(path, contents))?,
+        }
+        journal::after_change(path, Some(contents))
+    }
+
+    pub fn remove(&self, path: &str) -> Result<()> {
+        journal::before_change(self, path)?;
+        match self {
+            Storage::WorkTree => {
+                let pm_dir = pm_dir()?;
+                let full_path = pm_dir.join(path);
+                std::fs::remove_file(&full_path)
+                    .wrap_err_with(|| format!("deleting file {:?}", &full_path))?;
+                // git does not track empty directories, so prune them to match the tree
+                // of the board ref
+                let mut dir = full_path.parent();
+                while let Some(d) = dir.filter(|d| *d != pm_dir) {
+                    if std::fs::remove_dir(d).is_err() {
+                        break;
+                    }
+                    dir = d.parent();
+                }
+            }
+            Storage::Ref => with_ref_board(|board| board.remove(path))?,
+        }
+        journal::after_change(path, None)
+    }
+
+    /// Move a file within the board
+    pub fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let contents = self
+            .read(from)?
+            .ok_or_else(|| eyre::eyre!("could not find {}", from))?;
+        self.write(to, &contents)?;
+        self.remove(from)
+    }
+
+    /// Make a file available on disk for external tools such as an editor. Changes to
+    /// the returned path must be handed back with `checkin`.
+    pub fn checkout(&self, path: &str) -> Result<PathBuf> {
+        match self {
+            Storage::WorkTree => {
+                // the file is changed in place, so note its contents for the journal now
+                journal::before_change(self, path)?;
+                Ok(pm_dir()?.join(path))
+            }
+            Storage::Ref => {
+                let contents = self
+                    .read(path)?
+                    .ok_or_else(|| eyre::eyre!("could not find {} on {}", path, BOARD_REF))?;
+                let local_path = git::git_dir()?.join("pm-checkout").join(path);
+                ensure_parent_dir(&local_path)?;
+                std::fs::write(&local_path, contents)
+                    .wrap_err_with(|| format!("writing {:?}", &local_path))?;
+                Ok(local_path)
+            }
+        }
+    }
+
+    pub fn checkin(&self, path: &str, local_path: &Path) -> Result<()> {
+        match self {
+            Storage::WorkTree => {
+                let contents = std::fs::read(local_path)
+                    .wrap_err_with(|| format!("reading {:?}", local_path))?;
+                journal::after_change(path, Some(&contents))
+            }
+            Storage::Ref => {
+                let contents = std::fs::read(local_path)
+                    .wrap_err_with(|| format!("reading {:?}", local_path))?;
+                self.write(path, &contents)?;
+                std::fs::remove_file(local_path)
+                    .wrap_err_with(|| format!("deleting file {:?}", local_path))
+            }
+        }
+    }
+}
+
+/// Commit the changes the running command staged on the board ref, if any, so that
+/// each command adds a single commit to the ref
+pub fn commit(message: &str) -> Result<()> {
+    let mut board = REF_BOARD.lock().unwrap();
+    match board.as_mut() {
+        Some(board) => board.commit(message),
+        None => Ok(()),
+    }
+}
+
+fn with_ref_board<T>(f: impl FnOnce(&mut RefBoard) -> Result<T>) -> Result<T> {
+    let mut board = REF_BOARD.lock().unwrap();
+    if board.is_none() {
+        *board = Some(RefBoard::new(git::git_dir()?));
+    }
+    // unwrap is safe because the board was opened above
+    f(board.as_mut().unwrap())
+}
+
+/// The board kept as a tree on `BOARD_REF`. Changes are staged in a private git index
+/// rather than the index of the working tree, and read back from it until they are
+/// committed.
+struct RefBoard {
+    git_dir: PathBuf,
+    /// Whether the staging index holds changes which are not yet committed
+    staged: bool,
+}
+
+impl RefBoard {
+    fn new(git_dir: PathBuf) -> RefBoard {
+        RefBoard {
+            git_dir,
+            staged: false,
+        }
+    }
+
+    fn git(&self) -> Command {
+        let mut command = Command::new("git");
+        command
+            .env("GIT_DIR", &self.git_dir)
+            .env("GIT_INDEX_FILE", self.git_dir.join("pm-index"));
+        command
+    }
+
+    fn run(&self, args: &[&str], input: Option<&[u8]>) -> Result<String> {
+        let mut command = self.git();
+        command.args(args);
+        let output = git::run_command(command, input)?;
+        Ok(String::from_utf8_lossy(&output).trim().to_string())
+    }
+
+    /// The commit the board ref points to, or `None` before the first commit
+    fn head(&self) -> Result<Option<String>> {
+        let head = self.run(&["for-each-ref", "--format=%(objectname)", BOARD_REF], None)?;
+        Ok(Some(head).filter(|h| !h.is_empty()))
+    }
+
+    /// The object holding a file, from the staged changes if there are any
+    fn spec(&self, path: &str) -> String {
+        if self.staged {
+            format!(":{}", path)
+        } else {
+            format!("{}:{}", BOARD_REF, path)
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let spec = self.spec(path);
+        let output = self
+            .git()
+            .args(["cat-file", "blob", &spec])
+            .output()
+            .wrap_err_with(|| format!("running git cat-file {}", spec))?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(output.stdout))
+    }
+
+    fn read_at(&self, rev: &str, path: &str) -> Result<Option<String>> {
+        let spec = format!("{}:{}", rev, path);
+        let output = self
+            .git()
+            .args(["cat-file", "blob", &spec])
+            .output()
+            .wrap_err_with(|| format!("running git cat-file {}", spec))?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let contents = String::from_utf8(output.stdout).wrap_err("decoding git output")?;
+        Ok(Some(contents))
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        let spec = self.spec(path);
+        let output = self
+            .git()
+            .args(["cat-file", "-e", &spec])
+            .output()
+            .wrap_err_with(|| format!("running git cat-file {}", spec))?;
+        Ok(output.status.success())
+    }
+
+    fn list(&self, dir: &str) -> Result<Vec<String>> {
+        let dir = format!("{}/", dir.trim_end_matches('/'));
+        let files = if self.staged {
+            self.run(&["ls-files", "--", &dir], None)?
+        } else {
+            match self.head()? {
+                Some(head) => {
+                    self.run(&["ls-tree", "-r", "--name-only", &head, "--", &dir], None)?
+                }
+                None => String::new(),
+            }
+        };
+        Ok(files.lines().map(String::from).collect())
+    }
+
+    /// Start staging changes from the tree of the ref, unless some are staged already
+    fn stage(&mut self) -> Result<()> {
+        if self.staged {
+            return Ok(());
+        }
+        match self.head()? {
+            Some(head) => self.run(&["read-tree", &head], None),
+            None => self.run(&["read-tree", "--empty"], None),
+        }
+        .wrap_err("reading board tree")?;
+        self.staged = true;
+        Ok(())
+    }
+
+    fn write(&mut self, path: &str, contents: &[u8]) -> Result<()> {
+        self.stage()?;
+        let blob = git::write_blob_with(self.git(), contents)?;
+        let cacheinfo = format!("100644,{},{}", blob, path);
+        self.run(&["update-index", "--add", "--cacheinfo", &cacheinfo], None)
+            .wrap_err("updating board tree")?;
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.stage()?;
+        self.run(&["update-index", "--force-remove", path], None)
+            .wrap_err("updating board tree")?;
+        Ok(())
+    }
+
+    /// Commit the staged changes, unless they leave the tree as it was
+    fn commit(&mut self, message: &str) -> Result<()> {
+        if !self.staged {
+            return Ok(());
+        }
+        self.staged = false;
+        let tree = self
+            .run(&["write-tree"], None)
+            .wrap_err("writing board tree")?;
+        let parent = self.head()?;
+        if let Some(parent) = &parent {
+            let parent_tree = self
+                .run(&["rev-parse", &format!("{}^{{tree}}", parent)], None)
+                .wrap_err("reading board tree")?;
+            if parent_tree == tree {
+                return Ok(());
+            }
+        }
+
+        let message = format!("git pm: {}", message);
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", message.as_str()];
+        if let Some(parent) = &parent {
+            args.push("-p");
+            args.push(parent);
+        }
+        let commit = self.run(&args, None).wrap_err("committing board")?;
+
+        let mut args = vec!["update-ref", BOARD_REF, commit.as_str()];
+        if let Some(parent) = &parent {
+            args.push(parent);
+        }
+        self.run(&args, None).wrap_err("updating board ref")?;
+        Ok(())
+    }
+}
+
+fn pm_dir() -> Result<PathBuf> {
+    find_project_root()
+        .map(|r| r.join("pm"))
+        .wrap_err("computing pm dir")
+}
+
+pub fn find_project_root() -> Result<PathBuf> {
+    let mut cwd = std::env::current_dir().wrap_err("getting current dir")?;
+    loop {
+        if cwd == Path::new("/") {
+            return Err(eyre::eyre!("could not find root path for git repository"));
+        }
+        if cwd.join(".git").is_dir() {
+            return Ok(cwd);
+        }
+        cwd = cwd.join("..").canonicalize()?;
+    }
+}
+
+fn ensure_parent_dir(p: &Path) -> Result<()> {
+    // unwrap is safe because all storage paths have at least one component
+    let parent_dir = p.parent().unwrap();
+    std::fs::create_dir_all(parent_dir)
+        .wrap_err_with(|| format!("creating directory {:?}", parent_dir))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board on a new, empty repository in the temporary directory
+    fn board(name: &str) -> RefBoard {
+        let dir = std::env::temp_dir().join(format!("git-pm-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        git::run(&["init", "--quiet", &dir.to_string_lossy()]).unwrap();
+        let board = RefBoard::new(dir.join(".git"));
+        board
+            .run(&["config", "user.name", "Jane Doe"], None)
+            .unwrap();
+        board
+            .run(&["config", "user.email", "jane@example.com"], None)
+            .unwrap();
+        board
+    }
+
+    fn commit_count(board: &RefBoard) -> String {
+        board
+            .run(&["rev-list", "--count", BOARD_REF], None)
+            .unwrap()
+    }
+
+    #[test]
+    fn ref_board_write_and_read_at() {
+        let mut board = board("write");
+        board.write("index.yml", b"tasks: []").unwrap();
+        board.write("tasks/001.md", b"first").unwrap();
+        assert_eq!(
+            board.read("index.yml").unwrap(),
+            Some(b"tasks: []".to_vec())
+        );
+        assert!(board.exists("tasks/001.md").unwrap());
+        assert_eq!(board.read_at(BOARD_REF, "index.yml").unwrap(), None);
+
+        board.commit("add 1").unwrap();
+        assert_eq!(commit_count(&board), "1");
+        assert_eq!(
+            board.read_at(BOARD_REF, "tasks/001.md").unwrap(),
+            Some("first".to_string())
+        );
+        assert_eq!(board.read("tasks/001.md").unwrap(), Some(b"first".to_vec()));
+
+        // changes which leave the tree as it was add no commit
+        board.write("tasks/001.md", b"first").unwrap();
+        board.commit("edit 1").unwrap();
+        assert_eq!(commit_count(&board), "1");
+
+        let _ = std::fs::remove_dir_all(board.git_dir.parent().unwrap());
+    }
+
+    #[test]
+    fn ref_board_remove() {
+        let mut board = board("remove");
+        board.write("index.yml", b"tasks: []").unwrap();
+        board.write("tasks/001.md", b"first").unwrap();
+        board.commit("add 1").unwrap();
+
+        board.remove("tasks/001.md").unwrap();
+        board.write("index.yml", b"tasks: [] # empty").unwrap();
+        assert_eq!(board.read("tasks/001.md").unwrap(), None);
+        assert!(!board.exists("tasks/001.md").unwrap());
+        assert!(board.read_at(BOARD_REF, "tasks/001.md").unwrap().is_some());
+
+        board.commit("delete 1").unwrap();
+        assert_eq!(commit_count(&board), "2");
+        assert_eq!(board.read_at(BOARD_REF, "tasks/001.md").unwrap(), None);
+        assert_eq!(
+            board
+                .read_at(&format!("{}^", BOARD_REF), "tasks/001.md")
+                .unwrap(),
+            Some("first".to_string())
+        );
+
+        let _ = std::fs::remove_dir_all(board.git_dir.parent().unwrap());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::index::{Status, Task};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Period::Day => write!(f, "day"),
+            Period::Week => write!(f, "week"),
+            Period::Month => write!(f, "month"),
+        }
+    }
+}
+
+impl std::str::FromStr for Period {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            other => Err(eyre::eyre!("invalid period {}", other)),
+        }
+    }
+}
+
+impl Period {
+    /// The first day of the period containing `date`. Weeks start on Monday.
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            // unwrap is safe because every month has a first day
+            Period::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// The first day of the period following the one starting at `start`
+    pub fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => start + Duration::days(1),
+            Period::Week => start + Duration::days(7),
+            Period::Month => {
+                let (year, month) = if start.month() == 12 {
+                    (start.year() + 1, 1)
+                } else {
+                    (start.year(), start.month() + 1)
+                };
+                // unwrap is safe because every month has a first day
+                NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+            }
+        }
+    }
+}
+
+/// The number of tasks finished in a single period
+#[derive(Debug, PartialEq)]
+pub struct Bucket {
+    pub start: NaiveDate,
+    pub done: usize,
+    /// The mean of this and the preceding periods, up to the trailing window size
+    pub average: f64,
+}
+
+/// Count the tasks finished in each period, from the first finished task up to the
+/// period containing `today`
+pub fn buckets(tasks: &[Task], period: Period, window: usize, today: NaiveDate) -> Vec<Bucket> {
+    let finished: Vec<NaiveDate> = tasks
+        .iter()
+        .filter(|t| t.status == Status::Done)
+        .filter_map(|t| t.finished_on())
+        .map(|on| on.naive_utc().date())
+        .collect();
+    let first = match finished.iter().min() {
+        Some(first) => period.start(*first),
+        None => return Vec::new(),
+    };
+
+    let mut buckets: Vec<Bucket> = Vec::new();
+    let mut start = first;
+    while start <= today {
+        let end = period.next(start);
+        let done = finished
+            .iter()
+            .filter(|d| **d >= start && **d < end)
+            .count();
+        let trailing: Vec<usize> = buckets
+            .iter()
+            .rev()
+            .take(window.max(1) - 1)
+            .map(|b| b.done)
+            .chain(std::iter::once(done))
+            .collect();
+        let average = trailing.iter().sum::<usize>() as f64 / trailing.len() as f64;
+        buckets.push(Bucket {
+            start,
+            done,
+            average,
+        });
+        start = end;
+    }
+    buckets
+}
+
+/// The recent number of tasks finished per period: the trailing average as of the last
+/// complete period, so that a period which has only just started does not drag it down
+pub fn rate(buckets: &[Bucket], period: Period, today: NaiveDate) -> f64 {
+    buckets
+        .iter()
+        .rev()
+        .find(|b| period.next(b.start) <= today)
+        .map(|b| b.average)
+        .unwrap_or(0.0)
+}
+
+/// When `remaining` tasks would be finished at `rate` tasks per period, counting from
+/// the period starting at `from`
+pub fn forecast(remaining: usize, rate: f64, period: Period, from: NaiveDate) -> Option<NaiveDate> {
+    if rate <= 0.0 {
+        return None;
+    }
+    let periods = (remaining as f64 / rate).ceil() as usize;
+    let mut date = from;
+    for _ in 0..periods {
+        date = period.next(date);
+    }
+    Some(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Change;
+    use chrono::{TimeZone, Utc};
+
+    fn finished_task(id: u64, date: NaiveDate) -> Task {
+        Task {
+            id,
+            status: Status::Done,
+            changes: vec![Change {
+                from: Status::Doing,
+                to: Status::Done,
+                on: Utc.from_utc_datetime(&date.and_hms_opt(12, 0, 0).unwrap()),
+                by: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn period_starts() {
+        // 2021-09-19 was a Sunday
+        let date = NaiveDate::from_ymd_opt(2021, 9, 19).unwrap();
+        assert_eq!(
+            Period::Week.start(date),
+            NaiveDate::from_ymd_opt(2021, 9, 13).unwrap()
+        );
+        assert_eq!(
+            Period::Month.start(date),
+            NaiveDate::from_ymd_opt(2021, 9, 1).unwrap()
+        );
+        assert_eq!(
+            Period::Month.next(NaiveDate::from_ymd_opt(2021, 12, 1).unwrap()),
+            NaiveDate::from_ymd_opt(2022, 1, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn weekly_buckets_with_trailing_average() {
+        let tasks = vec![
+            finished_task(1, NaiveDate::from_ymd_opt(2021, 9, 13).unwrap()),
+            finished_task(2, NaiveDate::from_ymd_opt(2021, 9, 14).unwrap()),
+            finished_task(3, NaiveDate::from_ymd_opt(2021, 9, 28).unwrap()),
+        ];
+
+        let buckets = buckets(
+            &tasks,
+            Period::Week,
+            2,
+            NaiveDate::from_ymd_opt(2021, 9, 30).unwrap(),
+        );
+        let counts: Vec<_> = buckets.iter().map(|b| (b.done, b.average)).collect();
+        assert_eq!(counts, vec![(2, 2.0), (0, 1.0), (1, 0.5)]);
+    }
+
+    #[test]
+    fn rate_ignores_unfinished_period() {
+        let tasks: Vec<_> = (0..6)
+            .map(|i| {
+                let day = 13 + (i / 2) * 7 + i % 2;
+                finished_task(i as u64 + 1, NaiveDate::from_ymd_opt(2021, 9, day).unwrap())
+            })
+            .collect();
+
+        // on the Tuesday of the fourth week nothing has been finished in it yet
+        let today = NaiveDate::from_ymd_opt(2021, 10, 5).unwrap();
+        let buckets = buckets(&tasks, Period::Week, 4, today);
+        assert_eq!(buckets.last().map(|b| b.average), Some(1.5));
+        assert_eq!(rate(&buckets, Period::Week, today), 2.0);
+        assert_eq!(rate(&buckets[..1], Period::Week, today), 2.0);
+        assert_eq!(rate(&[], Period::Week, today), 0.0);
+    }
+
+    #[test]
+    fn forecast_backlog() {
+        let from = NaiveDate::from_ymd_opt(2021, 9, 13).unwrap();
+        assert_eq!(
+            forecast(5, 2.0, Period::Week, from),
+            Some(NaiveDate::from_ymd_opt(2021, 10, 4).unwrap())
+        );
+        assert_eq!(forecast(5, 0.0, Period::Week, from), None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::git;
+use crate::journal;
+use crate::storage;
+use eyre::{Result, WrapErr};
+use std::fmt;
+
+/// Markers which start a comment in the languages we are likely to come across. A
+/// `TODO` is only picked up when one of these comes before it on the line.
+const COMMENT_MARKERS: [&str; 6] = ["//", "/*", "*", "#", "--", ";"];
+
+/// A `TODO` comment in a tracked file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Todo {
+    /// The path of the file, relative to the repository root
+    pub path: String,
+    pub line: usize,
+    /// The task the comment is linked to with `TODO(pm#12)`
+    pub task: Option<u64>,
+    /// The rest of the comment after the `TODO`
+    pub text: String,
+}
+
+impl Todo {
+    /// A summary for a new task tracking this comment
+    pub fn summary(&self) -> String {
+        if self.text.is_empty() {
+            format!("TODO in {}:{}", self.path, self.line)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+impl fmt::Display for Todo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path, self.line)?;
+        if !self.text.is_empty() {
+            write!(f, "  {}", self.text)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Find the `TODO` comment in a line, returning the byte offset of the `TODO`, the task
+/// it is linked to and the rest of the comment
+fn parse_line(line: &str) -> Option<(usize, Option<u64>, String)> {
+    let start = line.match_indices("TODO").map(|(i, _)| i).find(|i| {
+        let before = line[..*i].chars().next_back();
+        let after = line[i + 4..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })?;
+    if !COMMENT_MARKERS.iter().any(|m| line[..start].contains(m)) {
+        return None;
+    }
+
+    let mut rest = &line[start + 4..];
+    let mut task = None;
+    if let Some(tag) = rest.strip_prefix('(') {
+        if let Some(end) = tag.find(')') {
+            task = tag[..end]
+                .strip_prefix("pm#")
+                .and_then(|id| id.parse().ok());
+            rest = &tag[end + 1..];
+        }
+    }
+    let text = rest
+        .trim_start_matches(|c: char| c == ':' || c == '-' || c.is_whitespace())
+        .trim_end()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim_end();
+    Some((start, task, text.to_string()))
+}
+
+fn from_match(m: git::GrepMatch) -> Option<Todo> {
+    let (_, task, text) = parse_line(&m.text)?;
+    Some(Todo {
+        path: m.path,
+        line: m.line,
+        task,
+        text,
+    })
+}
+
+/// Every `TODO` comment in the tracked files of the repository, outside the board
+pub fn scan() -> Result<Vec<Todo>> {
+    let matches = git::grep(r"(^|[^[:alnum:]_])TODO([^[:alnum:]_]|$)", false)
+        .wrap_err("searching for TODO comments")?;
+    Ok(matches.into_iter().filter_map(from_match).collect())
+}
+
+/// The `TODO(pm#N)` comments linked to a task
+pub fn linked_to(task_id: u64) -> Result<Vec<Todo>> {
+    let matches = git::grep(&format!("TODO(pm#{})", task_id), true)
+        .wrap_err("searching for TODO comments")?;
+    Ok(matches
+        .into_iter()
+        .filter_map(from_match)
+        .filter(|t| t.task == Some(task_id))
+        .collect())
+}
+
+/// Rewrite a plain `TODO` comment as `TODO(pm#N)` to link it to a task
+pub fn link(todo: &Todo, task_id: u64) -> Result<()> {
+    let path = storage::find_project_root()?.join(&todo.path);
+    let contents =
+        std::fs::read_to_string(&path).wrap_err_with(|| format!("reading {:?}", path))?;
+    let mut lines: Vec<String> = contents.split_inclusive('\n').map(String::from).collect();
+    let line = lines
+        .get_mut(todo.line - 1)
+        .ok_or_else(|| eyre::eyre!("{} has changed since it was scanned", todo.path))?;
+    let (start, _, _) = parse_line(line)
+        .ok_or_else(|| eyre::eyre!("{} has changed since it was scanned", todo.path))?;
+    let rest = &line[start + 4..];
+    // replace an existing tag such as `TODO(alice)` rather than nesting it
+    let rest = match rest
+        .strip_prefix('(')
+        .and_then(|r| r.find(')').map(|e| &r[e + 1..]))
+    {
+        Some(after_tag) => after_tag,
+        None => rest,
+    };
+    *line = format!("{}TODO(pm#{}){}", &line[..start], task_id, rest);
+    let contents = lines.concat();
+    journal::before_source_change(&todo.path)?;
+    std::fs::write(&path, &contents).wrap_err_with(|| format!("writing {:?}", path))?;
+    journal::after_source_change(&todo.path, contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_todo_comments() {
+        assert_eq!(
+            parse_line("    // TODO(pm#12): handle errors"),
+            Some((7, Some(12), "handle errors".to_string()))
+        );
+        assert_eq!(
+            parse_line("x = 1  # TODO tidy this up"),
+            Some((9, None, "tidy this up".to_string()))
+        );
+        assert_eq!(
+            parse_line("/* TODO(srw) - roll back */"),
+            Some((3, None, "roll back".to_string()))
+        );
+        assert_eq!(parse_line("let todo = \"TODO\";"), None);
+        assert_eq!(parse_line("// TODOS are fine"), None);
+    }
+}
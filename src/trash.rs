@@ -0,0 +1,102 @@
+use crate::index::Task;
+use crate::storage::Storage;
+use chrono::{DateTime, Duration, Utc};
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+
+/// The index of deleted tasks, relative to the board root
+pub const TRASH_INDEX_FILE: &str = "trash/index.yml";
+
+/// The path of a deleted task's detail file, relative to the board root
+pub fn detail_file(task_id: u64) -> String {
+    format!("trash/tasks/{:03}.md", task_id)
+}
+
+/// A task deleted from the board, with what is needed to put it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trashed {
+    pub task: Task,
+    pub deleted_on: DateTime<Utc>,
+    /// Who deleted the task, as `Name <email>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<String>,
+    /// The tasks which were blocked by this one when it was deleted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked: Vec<u64>,
+    /// The subtasks of this one when it was deleted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<u64>,
+}
+
+impl Trashed {
+    /// Whether the task was deleted more than `age` before `now`
+    pub fn is_older_than(&self, age: Duration, now: DateTime<Utc>) -> bool {
+        now - self.deleted_on > age
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Trash {
+    tasks: Vec<Trashed>,
+}
+
+/// The deleted tasks, in the order they were deleted
+pub fn load() -> Result<Vec<Trashed>> {
+    match Storage::current()?
+        .read_to_string(TRASH_INDEX_FILE)
+        .wrap_err("reading trash index")?
+    {
+        Some(contents) => {
+            let trash: Trash = serde_yaml::from_str(&contents).wrap_err("parsing trash index")?;
+            Ok(trash.tasks)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Replace the deleted tasks, removing the trash index if there are none left
+pub fn save(tasks: &[Trashed]) -> Result<()> {
+    let storage = Storage::current()?;
+    if tasks.is_empty() {
+        if storage.exists(TRASH_INDEX_FILE)? {
+            storage
+                .remove(TRASH_INDEX_FILE)
+                .wrap_err("deleting trash index")?;
+        }
+        return Ok(());
+    }
+    let trash = Trash {
+        tasks: tasks.to_vec(),
+    };
+    let body = serde_yaml::to_string(&trash).wrap_err("serializing trash index")?;
+    storage
+        .write(TRASH_INDEX_FILE, body.as_bytes())
+        .wrap_err("writing trash index")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trash() {
+        let text = r#"
+tasks:
+- task:
+    id: 3
+    status: Todo
+    changes: []
+  deleted_on: 2021-09-01T12:00:00+00:00
+  blocked: [4]
+"#;
+        let trash: Trash = serde_yaml::from_str(text).unwrap();
+        let trashed = &trash.tasks[0];
+        assert_eq!(trashed.task.id, 3);
+        assert_eq!(trashed.blocked, vec![4]);
+        assert!(trashed.children.is_empty());
+
+        let now = "2021-10-02T12:00:00Z".parse().unwrap();
+        assert!(trashed.is_older_than(Duration::days(30), now));
+        assert!(!trashed.is_older_than(Duration::days(31), now));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::index::{detail_file, Index, Task, TaskDetail};
+use crate::storage::Storage;
+use eyre::{Result, WrapErr};
+use std::fmt;
+
+pub fn references(text: &str) -> Vec<u64> {
+    let mut ids = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        if *c != '#' {
+            continue;
+        }
+        // `C#1` or `&#39;` are not references
+        if i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '&' || chars[i - 1] == '#') {
+            continue;
+        }
+        let digits: String = chars[i + 1..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let end = i + 1 + digits.len();
+        if digits.is_empty() || chars.get(end).is_some_and(|c| c.is_alphanumeric()) {
+            continue;
+        }
+        if let Ok(id) = digits.parse() {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+pub fn mentions(task_id: u64, detail: &TaskDetail) -> Vec<u64> {
+    let text = format!("{}\n{}", detail.summary, detail.description);
+    references(&text)
+        .into_iter()
+        .filter(|id| *id != task_id)
+        .collect()
+}
+
+pub fn referenced_by(tasks: &[Task], task_id: u64) -> Vec<u64> {
+    let details = tasks.iter().filter(|t| t.id != task_id).map(|t| t.detail());
+    mentioning(details, task_id)
+}
+
+fn mentioning(details: impl IntoIterator<Item = Result<TaskDetail>>, task_id: u64) -> Vec<u64> {
+    details
+        .into_iter()
+        .filter_map(|d| d.ok())
+        .filter(|d| mentions(d.id, d).contains(&task_id))
+        .map(|d| d.id)
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    MissingDetail { task_id: u64 },
+    DanglingReference { task_id: u64, missing: u64 },
+    DanglingBlocker { task_id: u64, missing: u64 },
+    DanglingParent { task_id: u64, missing: u64 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingDetail { task_id } => {
+                write!(f, "task {:03} has no detail file", task_id)
+            }
+            Problem::DanglingReference { task_id, missing } => write!(
+                f,
+                "task {:03} refers to #{}, which does not exist",
+                task_id, missing
+            ),
+            Problem::DanglingBlocker { task_id, missing } => write!(
+                f,
+                "task {:03} is blocked by {:03}, which does not exist",
+                task_id, missing
+            ),
+            Problem::DanglingParent { task_id, missing } => write!(
+                f,
+                "task {:03} is part of {:03}, which does not exist",
+                task_id, missing
+            ),
+        }
+    }
+}
+
+pub fn check_task(index: &Index, task: &Task, detail: Option<&TaskDetail>) -> Vec<Problem> {
+    let exists = |id: u64| index.find_task(id).is_some();
+    let mut problems = Vec::new();
+    match detail {
+        // a task in the trash can still be restored, so mentioning it is fine
+        Some(detail) => problems.extend(
+            mentions(task.id, detail)
+                .into_iter()
+                .filter(|id| !exists(*id) && !index.is_trashed(*id))
+                .map(|missing| Problem::DanglingReference {
+                    task_id: task.id,
+                    missing,
+                }),
+        ),
+        None => problems.push(Problem::MissingDetail { task_id: task.id }),
+    }
+    problems.extend(
+        task.blocked_by
+            .iter()
+            .filter(|id| !exists(**id))
+            .map(|missing| Problem::DanglingBlocker {
+                task_id: task.id,
+                missing: *missing,
+            }),
+    );
+    if let Some(parent) = task.parent.filter(|id| !exists(*id)) {
+        problems.push(Problem::DanglingParent {
+            task_id: task.id,
+            missing: parent,
+        });
+    }
+    problems
+}
+
+pub fn fsck(index: &Index) -> Result<Vec<Problem>> {
+    let storage = Storage::current()?;
+    let mut problems = Vec::new();
+    for task in &index.tasks {
+        let detail = if storage.exists(&detail_file(task.id))? {
+            Some(
+                task.detail()
+                    .wrap_err_with(|| format!("fetching detail of task {}", task.id))?,
+            )
+        } else {
+            None
+        };
+        problems.extend(check_task(index, task, detail.as_ref()));
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trash::Trashed;
+    use chrono::Utc;
+
+    #[test]
+    fn find_references() {
+        assert_eq!(
+            references("Follow up on #12 and #3, see #12 again."),
+            vec![12, 3]
+        );
+        assert_eq!(references("# Heading\n## 2 Notes"), Vec::<u64>::new());
+        assert_eq!(references("C#1 &#39; #12a issue#4"), Vec::<u64>::new());
+        assert_eq!(references("(#7)"), vec![7]);
+    }
+
+    #[test]
+    fn references_to_trashed_tasks_are_not_dangling() {
+        let mut index = Index::new("Test").unwrap();
+        let task = Task {
+            id: 1,
+            ..Default::default()
+        };
+        index.tasks.push(task.clone());
+        index.trashed.push(Trashed {
+            task: Task {
+                id: 2,
+                ..Default::default()
+            },
+            deleted_on: Utc::now(),
+            deleted_by: None,
+            blocked: Vec::new(),
+            children: Vec::new(),
+        });
+        let detail = TaskDetail {
+            id: 1,
+            summary: "Follow up".to_string(),
+            description: "After #2 and #3".to_string(),
+            tags: Vec::new(),
+            assignees: Vec::new(),
+            estimate: None,
+            attachments: Vec::new(),
+        };
+
+        assert_eq!(
+            check_task(&index, &task, Some(&detail)),
+            vec![Problem::DanglingReference {
+                task_id: 1,
+                missing: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn referenced_by_skips_missing_details() {
+        let detail = |id, description: &str| TaskDetail {
+            id,
+            summary: format!("Task {}", id),
+            description: description.to_string(),
+            tags: Vec::new(),
+            assignees: Vec::new(),
+            estimate: None,
+            attachments: Vec::new(),
+        };
+        assert_eq!(
+            mentioning(
+                vec![
+                    Ok(detail(1, "See #3")),
+                    Err(eyre::eyre!("could not find task 2")),
+                    Ok(detail(4, "Follows #3")),
+                ],
+                3
+            ),
+            vec![1, 4]
+        );
+    }
+}